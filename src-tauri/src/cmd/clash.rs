@@ -1,6 +1,7 @@
 use super::CmdResult;
 use crate::{config::*, core::*, feat, module::mihomo::MihomoManager, wrap_err};
 use serde_yaml::Mapping;
+use std::collections::HashMap;
 
 /// 复制Clash环境变量
 #[tauri::command]
@@ -64,10 +65,62 @@ pub async fn clash_api_get_proxy_delay(
     name: String,
     url: Option<String>,
     timeout: i32,
-) -> CmdResult<serde_json::Value> {
+) -> CmdResult<mihomo_api::ProxyDelay> {
     MihomoManager::global()
         .test_proxy_delay(&name, url, timeout)
         .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取代理组内所有节点的延迟，由内核并发测试
+#[tauri::command]
+pub async fn clash_api_get_group_delay(
+    group: String,
+    url: Option<String>,
+    timeout: i32,
+    expected: Option<String>,
+) -> CmdResult<HashMap<String, u32>> {
+    MihomoManager::global()
+        .test_group_delay(&group, url, timeout, expected)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 批量测试节点延迟，结果通过 `verge://proxy-delay` 事件逐个推送
+#[tauri::command]
+pub async fn test_proxies_delay(
+    group: String,
+    names: Vec<String>,
+    url: Option<String>,
+    timeout: i32,
+    concurrency: Option<usize>,
+) -> CmdResult {
+    let concurrency = concurrency.unwrap_or(10);
+    wrap_err!(feat::test_proxies_delay(group, names, url, timeout, concurrency).await)
+}
+
+/// 通过内核的DNS解析域名，`record_type` 默认为 `A`
+#[tauri::command]
+pub async fn dns_query(
+    name: String,
+    record_type: Option<String>,
+) -> CmdResult<mihomo_api::DnsQuery> {
+    MihomoManager::global()
+        .query_dns(&name, record_type.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 清空内核的 fake-ip 缓存
+#[tauri::command]
+pub async fn flush_fakeip() -> CmdResult {
+    wrap_err!(MihomoManager::global().flush_fakeip().await)
+}
+
+/// 分别通过内核和系统解析域名并比较结果
+#[tauri::command]
+pub async fn diagnose_dns(domain: String) -> CmdResult<feat::DnsDiagnosis> {
+    wrap_err!(feat::diagnose_dns(domain).await)
 }
 
 /// 测试URL延迟
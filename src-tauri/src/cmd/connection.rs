@@ -0,0 +1,27 @@
+use super::CmdResult;
+use crate::{module::mihomo::MihomoManager, wrap_err};
+use mihomo_api::{ConnectionFilter, Connections};
+
+/// 获取当前连接
+#[tauri::command]
+pub async fn get_connections() -> CmdResult<Connections> {
+    wrap_err!(MihomoManager::global().get_connections().await)
+}
+
+/// 关闭指定连接
+#[tauri::command]
+pub async fn close_connection(id: String) -> CmdResult {
+    wrap_err!(MihomoManager::global().close_connection(&id).await)
+}
+
+/// 关闭全部连接
+#[tauri::command]
+pub async fn close_all_connections() -> CmdResult {
+    wrap_err!(MihomoManager::global().close_all_connections().await)
+}
+
+/// 按 host / rule / chain / process 关闭连接，返回关闭的数量
+#[tauri::command]
+pub async fn close_connections(filter: ConnectionFilter) -> CmdResult<usize> {
+    wrap_err!(MihomoManager::global().close_connections(&filter).await)
+}
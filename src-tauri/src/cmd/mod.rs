@@ -8,6 +8,7 @@ pub type CmdResult<T = ()> = Result<T, String>;
 // Command modules
 pub mod app;
 pub mod clash;
+pub mod connection;
 pub mod media_unlock_checker;
 pub mod network;
 pub mod profile;
@@ -23,6 +24,7 @@ pub mod webdav;
 // Re-export all command functions for backwards compatibility
 pub use app::*;
 pub use clash::*;
+pub use connection::*;
 pub use media_unlock_checker::*;
 pub use network::*;
 pub use profile::*;
@@ -166,6 +166,62 @@ pub fn view_profile(app_handle: tauri::AppHandle, index: String) -> CmdResult {
     wrap_err!(help::open_file(app_handle, path))
 }
 
+/// 获取远程订阅保留的历史版本，从新到旧
+#[tauri::command]
+pub fn get_profile_versions(uid: String) -> CmdResult<Vec<PrfVersion>> {
+    wrap_err!(PrfHistory::new(&uid).list())
+}
+
+/// 比较历史版本到当前订阅内容的变化
+#[tauri::command]
+pub fn diff_profile_version(uid: String, version: String) -> CmdResult<PrfDiff> {
+    let current = {
+        let profiles = Config::profiles();
+        let profiles = profiles.latest();
+        wrap_err!(profiles.get_item(&uid).and_then(|item| item.read_file()))?
+    };
+    let old = wrap_err!(PrfHistory::new(&uid).read(&version))?;
+    wrap_err!(PrfDiff::new(&old, &current))
+}
+
+/// 将远程订阅恢复为某个历史版本
+#[tauri::command]
+pub async fn restore_profile_version(uid: String, version: String) -> CmdResult {
+    let data = wrap_err!(PrfHistory::new(&uid).read(&version))?;
+    let (item, previous, should_update) = {
+        let profiles = Config::profiles();
+        let mut profiles = profiles.latest();
+        let item = wrap_err!(profiles.get_item(&uid))?.clone();
+        let previous = wrap_err!(item.read_file())?;
+        wrap_err!(item.save_file(data))?;
+        // 恢复的内容与服务器不同，下次更新需要完整下载
+        wrap_err!(profiles.set_validators(&uid, None, None))?;
+        let should_update = Some(uid.clone()) == profiles.get_current()
+            || profiles.current_sources().contains(&uid);
+        (item, previous, should_update)
+    };
+    if !should_update {
+        return Ok(());
+    }
+
+    let error = match CoreManager::global().update_config().await {
+        Ok((true, _)) => {
+            handle::Handle::refresh_clash();
+            return Ok(());
+        }
+        Ok((false, error_msg)) => error_msg,
+        Err(err) => err.to_string(),
+    };
+    // 内核不接受恢复的版本，放回之前的内容
+    log_err!(item.save_file(previous));
+    log_err!(Config::profiles().latest().set_validators(
+        &uid,
+        item.etag.clone(),
+        item.last_modified.clone()
+    ));
+    Err(error)
+}
+
 /// 读取配置文件内容
 #[tauri::command]
 pub fn read_profile_file(index: String) -> CmdResult<String> {
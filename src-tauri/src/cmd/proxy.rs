@@ -1,8 +1,9 @@
 use super::CmdResult;
-use crate::module::mihomo::MihomoManager;
+use crate::{feat, module::mihomo::MihomoManager, wrap_err};
+use mihomo_api::{Proxies, ProxyProviders, RuleProviders};
 
 #[tauri::command]
-pub async fn get_proxies() -> CmdResult<serde_json::Value> {
+pub async fn get_proxies() -> CmdResult<Proxies> {
     let mannager = MihomoManager::global();
     let proxies = mannager
         .refresh_proxies()
@@ -13,7 +14,7 @@ pub async fn get_proxies() -> CmdResult<serde_json::Value> {
 }
 
 #[tauri::command]
-pub async fn get_providers_proxies() -> CmdResult<serde_json::Value> {
+pub async fn get_providers_proxies() -> CmdResult<ProxyProviders> {
     let mannager = MihomoManager::global();
     let providers = mannager
         .refresh_providers_proxies()
@@ -22,3 +23,37 @@ pub async fn get_providers_proxies() -> CmdResult<serde_json::Value> {
         .or_else(|_| Ok(mannager.get_providers_proxies()));
     providers
 }
+
+/// 切换代理组的节点
+#[tauri::command]
+pub async fn select_proxy(group: String, proxy: String) -> CmdResult {
+    wrap_err!(feat::select_proxy(group, proxy).await)
+}
+
+/// 更新指定的代理集合
+#[tauri::command]
+pub async fn update_proxy_provider(name: String) -> CmdResult {
+    wrap_err!(MihomoManager::global().update_proxy_provider(&name).await)
+}
+
+/// 对代理集合进行健康检查
+#[tauri::command]
+pub async fn healthcheck_proxy_provider(name: String) -> CmdResult {
+    wrap_err!(
+        MihomoManager::global()
+            .healthcheck_proxy_provider(&name)
+            .await
+    )
+}
+
+/// 获取规则集合
+#[tauri::command]
+pub async fn get_rule_providers() -> CmdResult<RuleProviders> {
+    wrap_err!(MihomoManager::global().get_rule_providers().await)
+}
+
+/// 更新指定的规则集合
+#[tauri::command]
+pub async fn update_rule_provider(name: String) -> CmdResult {
+    wrap_err!(MihomoManager::global().update_rule_provider(&name).await)
+}
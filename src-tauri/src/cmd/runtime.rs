@@ -1,5 +1,10 @@
 use super::CmdResult;
-use crate::{config::*, wrap_err};
+use crate::{
+    config::*,
+    convert::{self, ExportFormat, Exported},
+    enhance::{self, lint::Diagnostic, trace::TraceStep},
+    wrap_err,
+};
 use anyhow::Context;
 use serde_yaml::Mapping;
 use std::collections::HashMap;
@@ -23,6 +28,17 @@ pub fn get_runtime_yaml() -> CmdResult<String> {
         ))
 }
 
+/// 将运行时配置导出为其他客户端的格式
+#[tauri::command]
+pub fn export_runtime_config(format: ExportFormat) -> CmdResult<Exported> {
+    let runtime = Config::runtime();
+    let runtime = runtime.latest();
+    let config = runtime.config.as_ref();
+    wrap_err!(config
+        .ok_or(anyhow::anyhow!("failed to get the runtime config"))
+        .and_then(|config| convert::export_config(config, format)))
+}
+
 /// 获取运行时存在的键
 #[tauri::command]
 pub fn get_runtime_exists() -> CmdResult<Vec<String>> {
@@ -34,3 +50,15 @@ pub fn get_runtime_exists() -> CmdResult<Vec<String>> {
 pub fn get_runtime_logs() -> CmdResult<HashMap<String, Vec<(String, String)>>> {
     Ok(Config::runtime().latest().chain_logs.clone())
 }
+
+/// 获取运行时配置的检查结果
+#[tauri::command]
+pub fn get_runtime_diagnostics() -> CmdResult<Vec<Diagnostic>> {
+    Ok(Config::runtime().latest().diagnostics.clone())
+}
+
+/// 重新跑一遍增强，获取每一步的变化，不影响运行时配置
+#[tauri::command]
+pub async fn get_enhance_trace() -> CmdResult<Vec<TraceStep>> {
+    Ok(enhance::enhance_trace().await)
+}
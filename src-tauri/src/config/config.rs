@@ -142,10 +142,13 @@ impl Config {
     pub async fn generate() -> Result<()> {
         let (config, exists_keys, logs) = enhance::enhance().await;
 
+        let diagnostics = enhance::lint::lint_config(&config);
+
         *Config::runtime().draft() = IRuntime {
             config: Some(config),
             exists_keys,
             chain_logs: logs,
+            diagnostics,
         };
 
         Ok(())
@@ -3,13 +3,15 @@ mod clash;
 mod config;
 mod draft;
 mod encrypt;
+mod prfhistory;
 mod prfitem;
 mod profiles;
 mod runtime;
 mod verge;
 
 pub use self::{
-    clash::*, config::*, draft::*, encrypt::*, prfitem::*, profiles::*, runtime::*, verge::*,
+    clash::*, config::*, draft::*, encrypt::*, prfhistory::*, prfitem::*, profiles::*, runtime::*,
+    verge::*,
 };
 
 pub const DEFAULT_PAC: &str = r#"function FindProxyForURL(url, host) {
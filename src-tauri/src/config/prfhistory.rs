@@ -0,0 +1,218 @@
+use crate::utils::dirs;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::{fs, path::PathBuf};
+
+/// A fetched version of a remote profile
+/// saved as `profiles/history/<uid>/<id>.yaml`, the id is the fetch time in millis
+#[derive(Debug, Clone, Serialize)]
+pub struct PrfVersion {
+    pub id: String,
+    /// fetch time in seconds, the same unit as `PrfItem.updated`
+    pub updated: usize,
+    pub size: u64,
+}
+
+/// What changed between two versions of a profile
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+pub struct PrfDiff {
+    pub proxies: PrfChanges,
+    pub groups: PrfChanges,
+    /// number of rules before and after
+    pub rules: (usize, usize),
+}
+
+/// Names of the proxies or groups that were added, removed or changed
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+pub struct PrfChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+/// The fetched versions of one remote profile
+pub struct PrfHistory {
+    uid: String,
+}
+
+impl PrfHistory {
+    pub fn new(uid: &str) -> Self {
+        Self { uid: uid.into() }
+    }
+
+    fn dir(&self) -> Result<PathBuf> {
+        Ok(dirs::app_history_dir()?.join(&self.uid))
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        // the id comes from the frontend, it must not leave the directory
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+            bail!("invalid profile version \"{id}\"");
+        }
+        Ok(self.dir()?.join(format!("{id}.yaml")))
+    }
+
+    /// save `data` as the newest version and keep at most `limit` versions
+    pub fn save(&self, data: &str, limit: usize) -> Result<()> {
+        if limit == 0 {
+            return Ok(());
+        }
+        fs::create_dir_all(self.dir()?)?;
+        // nothing to keep when the content is the same as the newest one
+        if let Some(latest) = self.list()?.first() {
+            if self.read(&latest.id)? == data {
+                return Ok(());
+            }
+        }
+
+        let id = chrono::Local::now().timestamp_millis().to_string();
+        fs::write(self.path(&id)?, data).context("failed to save the profile version")?;
+
+        for version in self.list()?.iter().skip(limit) {
+            fs::remove_file(self.path(&version.id)?)?;
+        }
+        Ok(())
+    }
+
+    /// versions from the newest to the oldest
+    pub fn list(&self) -> Result<Vec<PrfVersion>> {
+        let dir = self.dir()?;
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut versions = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Ok(millis) = id.parse::<u64>() else {
+                continue;
+            };
+            versions.push(PrfVersion {
+                id: id.into(),
+                updated: (millis / 1000) as usize,
+                size: fs::metadata(&path)?.len(),
+            });
+        }
+        versions.sort_by_key(|v| std::cmp::Reverse(v.id.parse::<u64>().unwrap_or_default()));
+        Ok(versions)
+    }
+
+    pub fn read(&self, id: &str) -> Result<String> {
+        fs::read_to_string(self.path(id)?)
+            .with_context(|| format!("failed to read the profile version \"{id}\""))
+    }
+
+    /// remove all versions, when the profile is deleted
+    pub fn remove(&self) -> Result<()> {
+        let dir = self.dir()?;
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
+
+impl PrfDiff {
+    /// compare two profiles, `old` and `new` are yaml
+    pub fn new(old: &str, new: &str) -> Result<Self> {
+        let old: Mapping = serde_yaml::from_str(old).context("invalid yaml of the old version")?;
+        let new: Mapping = serde_yaml::from_str(new).context("invalid yaml of the new version")?;
+        let rules = |config: &Mapping| {
+            config
+                .get("rules")
+                .and_then(Value::as_sequence)
+                .map_or(0, Vec::len)
+        };
+
+        Ok(Self {
+            proxies: PrfChanges::new(&old, &new, "proxies"),
+            groups: PrfChanges::new(&old, &new, "proxy-groups"),
+            rules: (rules(&old), rules(&new)),
+        })
+    }
+}
+
+impl PrfChanges {
+    /// compare the named items of a list like `proxies`
+    pub(crate) fn new(old: &Mapping, new: &Mapping, key: &str) -> Self {
+        let named = |config: &Mapping| -> Vec<(String, Value)> {
+            config
+                .get(key)
+                .and_then(Value::as_sequence)
+                .into_iter()
+                .flatten()
+                .filter_map(|item| {
+                    let name = item.get("name")?.as_str()?;
+                    Some((name.to_string(), item.clone()))
+                })
+                .collect()
+        };
+        let (old, new) = (named(old), named(new));
+        let find = |items: &[(String, Value)], name: &str| {
+            items
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, item)| item.clone())
+        };
+
+        let mut changes = Self::default();
+        for (name, item) in new.iter() {
+            match find(&old, name) {
+                None => changes.added.push(name.clone()),
+                Some(old_item) if old_item != *item => changes.changed.push(name.clone()),
+                _ => {}
+            }
+        }
+        for (name, _) in old.iter() {
+            if find(&new, name).is_none() {
+                changes.removed.push(name.clone());
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old = r#"
+proxies:
+  - {name: a, type: ss, server: a.com, port: 1}
+  - {name: b, type: ss, server: b.com, port: 1}
+  - {name: c, type: ss, server: c.com, port: 1}
+proxy-groups:
+  - {name: G, type: select, proxies: [a, b, c]}
+rules: ["MATCH,G"]
+"#;
+        let new = r#"
+proxies:
+  - {name: a, type: ss, server: a.com, port: 1}
+  - {name: b, type: ss, server: b.com, port: 2}
+  - {name: d, type: ss, server: d.com, port: 1}
+proxy-groups:
+  - {name: G, type: select, proxies: [a, b, d]}
+  - {name: H, type: select, proxies: [a]}
+rules: ["DOMAIN,x.com,H", "MATCH,G"]
+"#;
+        let diff = PrfDiff::new(old, new).unwrap();
+        assert_eq!(diff.proxies.added, ["d"]);
+        assert_eq!(diff.proxies.removed, ["c"]);
+        assert_eq!(diff.proxies.changed, ["b"]);
+        assert_eq!(diff.groups.added, ["H"]);
+        assert_eq!(diff.groups.changed, ["G"]);
+        assert!(diff.groups.removed.is_empty());
+        assert_eq!(diff.rules, (1, 2));
+
+        assert_eq!(
+            PrfDiff::new(old, old).unwrap().proxies,
+            PrfChanges::default()
+        );
+    }
+}
@@ -1,19 +1,24 @@
-use crate::utils::{dirs, help, resolve::VERSION, tmpl};
+use crate::{
+    convert,
+    utils::{dirs, help, resolve::VERSION, tmpl},
+};
 use anyhow::{bail, Context, Result};
-use reqwest::StatusCode;
+use reqwest::{
+    header::{HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
 use serde::{Deserialize, Serialize};
-use serde_yaml::Mapping;
-use std::fs;
+use std::{fmt, fs};
 use sysproxy::Sysproxy;
 
-use super::Config;
+use super::{Config, DEFAULT_PROFILE_MAX_SIZE};
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct PrfItem {
     pub uid: Option<String>,
 
     /// profile item type
-    /// enum value: remote | local | script | merge
+    /// enum value: remote | local | aggregate | script | merge
     #[serde(rename = "type")]
     pub itype: Option<String>,
 
@@ -50,6 +55,22 @@ pub struct PrfItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub home: Option<String>,
 
+    /// `ETag` of the last download, sent as `If-None-Match`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+
+    /// `Last-Modified` of the last download, sent as `If-Modified-Since`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+
+    /// what could not be converted when importing a foreign config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<Vec<String>>,
+
+    /// how the last update of a remote profile went
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch: Option<PrfFetch>,
+
     /// the file data
     #[serde(skip)]
     pub file_data: Option<String>,
@@ -69,6 +90,52 @@ pub struct PrfExtra {
     pub expire: u64,
 }
 
+/// The way a remote profile is downloaded
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FetchPath {
+    Direct,
+    /// through the mixed port of the app's own core
+    SelfProxy,
+    /// through the proxy set in the system
+    SystemProxy,
+}
+
+impl FetchPath {
+    /// the path asked for by `self_proxy` or `with_proxy`
+    pub fn from_option(option: Option<&PrfOption>) -> Self {
+        match option {
+            Some(PrfOption {
+                self_proxy: Some(true),
+                ..
+            }) => FetchPath::SelfProxy,
+            Some(PrfOption {
+                with_proxy: Some(true),
+                ..
+            }) => FetchPath::SystemProxy,
+            _ => FetchPath::Direct,
+        }
+    }
+}
+
+impl fmt::Display for FetchPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchPath::Direct => write!(f, "direct"),
+            FetchPath::SelfProxy => write!(f, "self proxy"),
+            FetchPath::SystemProxy => write!(f, "system proxy"),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct PrfFetch {
+    /// the path that succeeded, `None` when every path failed
+    pub path: Option<FetchPath>,
+    /// the errors of the failed attempts in order
+    pub errors: Vec<String>,
+}
+
 #[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct PrfOption {
     /// for `remote` profile's http request
@@ -104,6 +171,13 @@ pub struct PrfOption {
     pub proxies: Option<String>,
 
     pub groups: Option<String>,
+
+    pub transform: Option<String>,
+
+    /// for `aggregate` profile
+    /// uids of the profiles whose proxies are merged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<String>>,
 }
 
 impl PrfOption {
@@ -122,6 +196,8 @@ impl PrfOption {
                 a.rules = b.rules.or(a.rules);
                 a.proxies = b.proxies.or(a.proxies);
                 a.groups = b.groups.or(a.groups);
+                a.transform = b.transform.or(a.transform);
+                a.sources = b.sources.or(a.sources);
                 Some(a)
             }
             t => t.0.or(t.1),
@@ -152,6 +228,11 @@ impl PrfItem {
                 let desc = item.desc.unwrap_or("".into());
                 PrfItem::from_local(name, desc, file_data, item.option)
             }
+            "aggregate" => {
+                let name = item.name.unwrap_or("Aggregate".into());
+                let desc = item.desc.unwrap_or("".into());
+                PrfItem::from_aggregate(name, desc, item.option)
+            }
             typ => bail!("invalid profile item type \"{typ}\""),
         }
     }
@@ -164,15 +245,59 @@ impl PrfItem {
         file_data: Option<String>,
         option: Option<PrfOption>,
     ) -> Result<PrfItem> {
-        let uid = help::get_uid("L");
+        // a sing-box, surge or quantumult x config is converted into a profile
+        // anything else is kept as it is and checked when it is activated
+        let (file_data, warnings) = match file_data {
+            Some(data) => match convert::convert_subscription(&data) {
+                Ok(Some(converted)) => (
+                    serde_yaml::to_string(&converted.config)?,
+                    Some(converted.warnings).filter(|w| !w.is_empty()),
+                ),
+                _ => (data, None),
+            },
+            None => (tmpl::ITEM_LOCAL.into(), None),
+        };
+        // `sources` left over when the form switched from aggregate to local
+        let option = option.map(|o| PrfOption { sources: None, ..o });
+        PrfItem::with_chain("L", "local", name, desc, file_data, warnings, option)
+    }
+
+    /// ## Aggregate type
+    /// a local profile whose file is the base config
+    /// the proxies of the `sources` profiles are merged into it when it is activated
+    pub fn from_aggregate(
+        name: String,
+        desc: String,
+        option: Option<PrfOption>,
+    ) -> Result<PrfItem> {
+        let mut option = option.unwrap_or_default();
+        option.sources.get_or_insert_with(Vec::new);
+        let file_data = tmpl::ITEM_LOCAL.into();
+        PrfItem::with_chain("A", "aggregate", name, desc, file_data, None, Some(option))
+    }
+
+    /// a local or aggregate item with its own merge, script, rules, proxies, groups and transform
+    /// the chain items missing in `option` are created
+    fn with_chain(
+        prefix: &str,
+        itype: &str,
+        name: String,
+        desc: String,
+        file_data: String,
+        warnings: Option<Vec<String>>,
+        option: Option<PrfOption>,
+    ) -> Result<PrfItem> {
+        let uid = help::get_uid(prefix);
         let file = format!("{uid}.yaml");
         let opt_ref = option.as_ref();
         let update_interval = opt_ref.and_then(|o| o.update_interval);
+        let sources = opt_ref.and_then(|o| o.sources.clone());
         let mut merge = opt_ref.and_then(|o| o.merge.clone());
         let mut script = opt_ref.and_then(|o| o.script.clone());
         let mut rules = opt_ref.and_then(|o| o.rules.clone());
         let mut proxies = opt_ref.and_then(|o| o.proxies.clone());
         let mut groups = opt_ref.and_then(|o| o.groups.clone());
+        let mut transform = opt_ref.and_then(|o| o.transform.clone());
 
         if merge.is_none() {
             let merge_item = PrfItem::from_merge(None)?;
@@ -201,9 +326,16 @@ impl PrfItem {
             Config::profiles().data().append_item(groups_item.clone())?;
             groups = groups_item.uid;
         }
+        if transform.is_none() {
+            let transform_item = PrfItem::from_transform()?;
+            Config::profiles()
+                .data()
+                .append_item(transform_item.clone())?;
+            transform = transform_item.uid;
+        }
         Ok(PrfItem {
             uid: Some(uid),
-            itype: Some("local".into()),
+            itype: Some(itype.into()),
             name: Some(name),
             desc: Some(desc),
             file: Some(file),
@@ -217,11 +349,17 @@ impl PrfItem {
                 rules,
                 proxies,
                 groups,
+                transform,
+                sources,
                 ..PrfOption::default()
             }),
             home: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
-            file_data: Some(file_data.unwrap_or(tmpl::ITEM_LOCAL.into())),
+            etag: None,
+            last_modified: None,
+            warnings,
+            fetch: None,
+            file_data: Some(file_data),
         })
     }
 
@@ -233,9 +371,35 @@ impl PrfItem {
         desc: Option<String>,
         option: Option<PrfOption>,
     ) -> Result<PrfItem> {
+        let path = FetchPath::from_option(option.as_ref());
+        PrfItem::fetch_url(url, name, desc, option, path, None, None)
+            .await?
+            .context("the remote profile was not modified")
+    }
+
+    /// ## Remote type
+    /// download the item again, `None` when the server answers `304 Not Modified`
+    /// to the `ETag` or `Last-Modified` of the last download
+    pub async fn from_url_if_modified(
+        url: &str,
+        option: Option<PrfOption>,
+        path: FetchPath,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    ) -> Result<Option<PrfItem>> {
+        PrfItem::fetch_url(url, None, None, option, path, etag, last_modified).await
+    }
+
+    async fn fetch_url(
+        url: &str,
+        name: Option<String>,
+        desc: Option<String>,
+        option: Option<PrfOption>,
+        path: FetchPath,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    ) -> Result<Option<PrfItem>> {
         let opt_ref = option.as_ref();
-        let with_proxy = opt_ref.is_some_and(|o| o.with_proxy.unwrap_or(false));
-        let self_proxy = opt_ref.is_some_and(|o| o.self_proxy.unwrap_or(false));
         let accept_invalid_certs =
             opt_ref.is_some_and(|o| o.danger_accept_invalid_certs.unwrap_or(false));
         let user_agent = opt_ref.and_then(|o| o.user_agent.clone());
@@ -245,10 +409,11 @@ impl PrfItem {
         let mut rules = opt_ref.and_then(|o| o.rules.clone());
         let mut proxies = opt_ref.and_then(|o| o.proxies.clone());
         let mut groups = opt_ref.and_then(|o| o.groups.clone());
+        let mut transform = opt_ref.and_then(|o| o.transform.clone());
         let mut builder = reqwest::ClientBuilder::new().use_rustls_tls().no_proxy();
 
         // 使用软件自己的代理
-        if self_proxy {
+        if path == FetchPath::SelfProxy {
             let port = Config::verge()
                 .latest()
                 .verge_mixed_port
@@ -267,7 +432,7 @@ impl PrfItem {
             }
         }
         // 使用系统代理
-        else if with_proxy {
+        else if path == FetchPath::SystemProxy {
             if let Ok(p @ Sysproxy { enable: true, .. }) = Sysproxy::get_system_proxy() {
                 let proxy_scheme = format!("http://{}:{}", p.host, p.port);
 
@@ -291,14 +456,37 @@ impl PrfItem {
         builder = builder.danger_accept_invalid_certs(accept_invalid_certs);
         builder = builder.user_agent(user_agent.unwrap_or(version));
 
-        let resp = builder.build()?.get(url).send().await?;
+        let max_size = Config::verge()
+            .latest()
+            .profile_max_size
+            .unwrap_or(DEFAULT_PROFILE_MAX_SIZE);
+
+        let mut request = builder.build()?.get(url);
+        if let Some(etag) = etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let resp = request.send().await?;
 
         let status_code = resp.status();
+        if status_code == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
         if !StatusCode::is_success(&status_code) {
             bail!("failed to fetch remote profile with status {status_code}")
         }
 
         let header = resp.headers();
+        let validator = |name: HeaderName| {
+            header
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = validator(ETAG);
+        let last_modified = validator(LAST_MODIFIED);
 
         // parse the Subscription UserInfo
         let extra = match header.get("Subscription-Userinfo") {
@@ -360,18 +548,25 @@ impl PrfItem {
         let uid = help::get_uid("R");
         let file = format!("{uid}.yaml");
         let name = name.unwrap_or(filename.unwrap_or("Remote File".into()));
-        let data = resp.text_with_charset("utf-8").await?;
+        let data = read_body(resp, max_size).await?;
 
         // process the charset "UTF-8 with BOM"
         let data = data.trim_start_matches('\u{feff}');
 
         // check the data whether the valid yaml format
-        let yaml = serde_yaml::from_str::<Mapping>(data)
-            .context("the remote profile data is invalid yaml")?;
-
-        if !yaml.contains_key("proxies") && !yaml.contains_key("proxy-providers") {
-            bail!("profile does not contain `proxies` or `proxy-providers`");
-        }
+        // share links, sing-box, surge and quantumult x configs are converted into a profile
+        let (data, warnings) = match convert::convert_subscription(data)? {
+            Some(converted) => {
+                for warning in converted.warnings.iter() {
+                    log::warn!(target: "app", "failed to convert {} of {url}, {warning}", converted.format);
+                }
+                (
+                    serde_yaml::to_string(&converted.config)?,
+                    Some(converted.warnings).filter(|w| !w.is_empty()),
+                )
+            }
+            None => (data.to_string(), None),
+        };
 
         if merge.is_none() {
             let merge_item = PrfItem::from_merge(None)?;
@@ -400,8 +595,15 @@ impl PrfItem {
             Config::profiles().data().append_item(groups_item.clone())?;
             groups = groups_item.uid;
         }
+        if transform.is_none() {
+            let transform_item = PrfItem::from_transform()?;
+            Config::profiles()
+                .data()
+                .append_item(transform_item.clone())?;
+            transform = transform_item.uid;
+        }
 
-        Ok(PrfItem {
+        Ok(Some(PrfItem {
             uid: Some(uid),
             itype: Some("remote".into()),
             name: Some(name),
@@ -417,12 +619,17 @@ impl PrfItem {
                 rules,
                 proxies,
                 groups,
+                transform,
                 ..PrfOption::default()
             }),
             home,
             updated: Some(chrono::Local::now().timestamp() as usize),
-            file_data: Some(data.into()),
-        })
+            etag,
+            last_modified,
+            warnings,
+            fetch: None,
+            file_data: Some(data),
+        }))
     }
 
     /// ## Merge type (enhance)
@@ -448,6 +655,10 @@ impl PrfItem {
             option: None,
             home: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
+            etag: None,
+            last_modified: None,
+            warnings: None,
+            fetch: None,
             file_data: Some(template),
         })
     }
@@ -473,6 +684,10 @@ impl PrfItem {
             extra: None,
             option: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
+            etag: None,
+            last_modified: None,
+            warnings: None,
+            fetch: None,
             file_data: Some(tmpl::ITEM_SCRIPT.into()),
         })
     }
@@ -494,6 +709,10 @@ impl PrfItem {
             extra: None,
             option: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
+            etag: None,
+            last_modified: None,
+            warnings: None,
+            fetch: None,
             file_data: Some(tmpl::ITEM_RULES.into()),
         })
     }
@@ -515,6 +734,10 @@ impl PrfItem {
             extra: None,
             option: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
+            etag: None,
+            last_modified: None,
+            warnings: None,
+            fetch: None,
             file_data: Some(tmpl::ITEM_PROXIES.into()),
         })
     }
@@ -536,10 +759,39 @@ impl PrfItem {
             extra: None,
             option: None,
             updated: Some(chrono::Local::now().timestamp() as usize),
+            etag: None,
+            last_modified: None,
+            warnings: None,
+            fetch: None,
             file_data: Some(tmpl::ITEM_GROUPS.into()),
         })
     }
 
+    /// ## Transform type (enhance)
+    pub fn from_transform() -> Result<PrfItem> {
+        let uid = help::get_uid("t");
+        let file = format!("{uid}.yaml"); // yaml ext
+
+        Ok(PrfItem {
+            uid: Some(uid),
+            itype: Some("transform".into()),
+            name: None,
+            desc: None,
+            file: Some(file),
+            url: None,
+            home: None,
+            selected: None,
+            extra: None,
+            option: None,
+            updated: Some(chrono::Local::now().timestamp() as usize),
+            etag: None,
+            last_modified: None,
+            warnings: None,
+            fetch: None,
+            file_data: Some(tmpl::ITEM_TRANSFORM.into()),
+        })
+    }
+
     /// get the file data
     pub fn read_file(&self) -> Result<String> {
         if self.file.is_none() {
@@ -562,3 +814,22 @@ impl PrfItem {
         fs::write(path, data.as_bytes()).context("failed to save the file")
     }
 }
+
+/// Read the body of a response up to `max_size` MB
+/// a larger body fails instead of being cut off
+async fn read_body(mut resp: reqwest::Response, max_size: u64) -> Result<String> {
+    // 0 would refuse every profile
+    let max_size = max_size.max(1);
+    let limit = max_size.saturating_mul(1024 * 1024);
+    if resp.content_length().is_some_and(|len| len > limit) {
+        bail!("the remote profile is larger than {max_size} MB");
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        if (body.len() + chunk.len()) as u64 > limit {
+            bail!("the remote profile is larger than {max_size} MB");
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
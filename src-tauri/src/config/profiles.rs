@@ -1,5 +1,12 @@
-use super::{prfitem::PrfItem, PrfOption};
-use crate::utils::{dirs, help};
+use super::{
+    prfitem::{PrfItem, PrfSelected},
+    Config, PrfHistory, PrfOption, DEFAULT_PROFILE_HISTORY_LIMIT,
+};
+use crate::{
+    enhance::aggregate::use_aggregate,
+    log_err,
+    utils::{dirs, help},
+};
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 use serde_yaml::Mapping;
@@ -38,6 +45,7 @@ impl IProfiles {
                         }
                     }
                 }
+                log_err!(profiles.append_missing_transforms());
                 profiles
             }
             Err(err) => {
@@ -47,6 +55,44 @@ impl IProfiles {
         }
     }
 
+    /// the local and remote items made before the proxy transform have no transform item
+    fn append_missing_transforms(&mut self) -> Result<()> {
+        let missing: Vec<String> = self
+            .items
+            .iter()
+            .flatten()
+            .filter(|item| matches!(item.itype.as_deref(), Some("remote" | "local")))
+            .filter(|item| {
+                item.option
+                    .as_ref()
+                    .and_then(|o| o.transform.as_ref())
+                    .is_none()
+            })
+            .filter_map(|item| item.uid.clone())
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        for uid in missing {
+            let transform = PrfItem::from_transform()?;
+            let option = PrfOption {
+                transform: transform.uid.clone(),
+                ..PrfOption::default()
+            };
+            self.append_item(transform)?;
+            if let Some(each) = self
+                .items
+                .iter_mut()
+                .flatten()
+                .find(|each| each.uid.as_ref() == Some(&uid))
+            {
+                each.option = PrfOption::merge(each.option.clone(), Some(option));
+            }
+        }
+        self.save_file()
+    }
+
     pub fn template() -> Self {
         Self {
             items: Some(vec![]),
@@ -103,6 +149,42 @@ impl IProfiles {
         bail!("failed to get the profile item \"uid:{uid}\"");
     }
 
+    /// nodes chosen by hand in the groups of the current profile
+    pub fn current_selected(&self) -> Vec<PrfSelected> {
+        self.current
+            .as_ref()
+            .and_then(|uid| self.get_item(uid).ok())
+            .and_then(|item| item.selected.clone())
+            .unwrap_or_default()
+    }
+
+    /// remember `proxy` as the node of `group` in the current profile
+    pub fn select_current(&mut self, group: &str, proxy: &str) -> Result<()> {
+        let Some(uid) = self.current.clone() else {
+            return Ok(());
+        };
+
+        let mut selected = self.current_selected();
+        match selected
+            .iter_mut()
+            .find(|each| each.name.as_deref() == Some(group))
+        {
+            Some(each) => each.now = Some(proxy.into()),
+            None => selected.push(PrfSelected {
+                name: Some(group.into()),
+                now: Some(proxy.into()),
+            }),
+        }
+
+        self.patch_item(
+            uid,
+            PrfItem {
+                selected: Some(selected),
+                ..PrfItem::default()
+            },
+        )
+    }
+
     /// append new item
     /// if the file_data is some
     /// then should save the data to file
@@ -126,11 +208,13 @@ impl IProfiles {
                 .with_context(|| format!("failed to create file \"{}\"", file))?
                 .write(file_data.as_bytes())
                 .with_context(|| format!("failed to write to file \"{}\"", file))?;
+
+            if item.itype.as_deref() == Some("remote") {
+                save_version(uid.as_deref().unwrap_or_default(), &file_data);
+            }
         }
 
-        if self.current.is_none()
-            && (item.itype == Some("remote".to_string()) || item.itype == Some("local".to_string()))
-        {
+        if self.current.is_none() && is_profile(&item) {
             self.current = uid;
         }
 
@@ -184,6 +268,7 @@ impl IProfiles {
                 patch!(each, item, extra);
                 patch!(each, item, updated);
                 patch!(each, item, option);
+                patch!(each, item, fetch);
 
                 self.items = Some(items);
                 return self.save_file();
@@ -195,7 +280,7 @@ impl IProfiles {
     }
 
     /// be used to update the remote item
-    /// only patch `updated` `extra` `file_data`
+    /// only patch `updated` `extra` `file_data` and the download validators
     pub fn update_item(&mut self, uid: String, mut item: PrfItem) -> Result<()> {
         if self.items.is_none() {
             self.items = Some(vec![]);
@@ -212,6 +297,9 @@ impl IProfiles {
                     each.extra = item.extra;
                     each.updated = item.updated;
                     each.home = item.home;
+                    each.warnings = item.warnings;
+                    each.etag = item.etag;
+                    each.last_modified = item.last_modified;
                     each.option = PrfOption::merge(each.option.clone(), item.option);
                     // save the file data
                     // move the field value after save
@@ -229,6 +317,7 @@ impl IProfiles {
                             .with_context(|| format!("failed to create file \"{}\"", file))?
                             .write(file_data.as_bytes())
                             .with_context(|| format!("failed to write to file \"{}\"", file))?;
+                        save_version(&uid, &file_data);
                     }
 
                     break;
@@ -239,6 +328,26 @@ impl IProfiles {
         self.save_file()
     }
 
+    /// set the download validators of the item
+    /// `patch_item` can't clear them
+    pub fn set_validators(
+        &mut self,
+        uid: &str,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    ) -> Result<()> {
+        let Some(each) = self.items.as_mut().and_then(|items| {
+            items
+                .iter_mut()
+                .find(|each| each.uid.as_deref() == Some(uid))
+        }) else {
+            bail!("failed to find the profile item \"uid:{uid}\"");
+        };
+        each.etag = etag;
+        each.last_modified = last_modified;
+        self.save_file()
+    }
+
     /// delete item
     /// if delete the current then return true
     pub fn delete_item(&mut self, uid: String) -> Result<bool> {
@@ -250,6 +359,7 @@ impl IProfiles {
         let rules_uid = item.option.as_ref().and_then(|e| e.rules.clone());
         let proxies_uid = item.option.as_ref().and_then(|e| e.proxies.clone());
         let groups_uid = item.option.as_ref().and_then(|e| e.groups.clone());
+        let transform_uid = item.option.as_ref().and_then(|e| e.transform.clone());
         let mut items = self.items.take().unwrap_or_default();
         let mut index = None;
         let mut merge_index = None;
@@ -257,6 +367,7 @@ impl IProfiles {
         let mut rules_index = None;
         let mut proxies_index = None;
         let mut groups_index = None;
+        let mut transform_index = None;
 
         // get the index
         for (i, _) in items.iter().enumerate() {
@@ -266,6 +377,7 @@ impl IProfiles {
             }
         }
         if let Some(index) = index {
+            log_err!(PrfHistory::new(&uid).remove());
             if let Some(file) = items.remove(index).file {
                 let _ = dirs::app_profiles_dir().map(|path| {
                     let path = path.join(file);
@@ -360,13 +472,28 @@ impl IProfiles {
                 });
             }
         }
+        // get the transform index
+        for (i, _) in items.iter().enumerate() {
+            if items[i].uid == transform_uid {
+                transform_index = Some(i);
+                break;
+            }
+        }
+        if let Some(index) = transform_index {
+            if let Some(file) = items.remove(index).file {
+                let _ = dirs::app_profiles_dir().map(|path| {
+                    let path = path.join(file);
+                    if path.exists() {
+                        let _ = fs::remove_file(path);
+                    }
+                });
+            }
+        }
         // delete the original uid
         if current == uid {
             self.current = None;
             for item in items.iter() {
-                if item.itype == Some("remote".to_string())
-                    || item.itype == Some("local".to_string())
-                {
+                if is_profile(item) {
                     self.current = item.uid.clone();
                     break;
                 }
@@ -387,7 +514,11 @@ impl IProfiles {
                         Some(file) => dirs::app_profiles_dir()?.join(file),
                         None => bail!("failed to get the file field"),
                     };
-                    return help::read_mapping(&file_path);
+                    let mapping = help::read_mapping(&file_path)?;
+                    if item.itype.as_deref() == Some("aggregate") {
+                        return Ok(use_aggregate(mapping, self.aggregate_sources(item)));
+                    }
+                    return Ok(mapping);
                 }
                 bail!("failed to find the current profile \"uid:{current}\"");
             }
@@ -395,6 +526,39 @@ impl IProfiles {
         }
     }
 
+    /// 聚合订阅引用的订阅名称和内容，找不到或读取失败的订阅会被跳过
+    fn aggregate_sources(&self, item: &PrfItem) -> Vec<(String, Mapping)> {
+        let sources = item.option.as_ref().and_then(|o| o.sources.clone());
+        let mut mappings = Vec::new();
+        for uid in sources.unwrap_or_default() {
+            // 聚合订阅不能再引用聚合订阅
+            let source = self
+                .get_item(&uid)
+                .ok()
+                .filter(|source| matches!(source.itype.as_deref(), Some("remote" | "local")));
+            let Some(source) = source else {
+                log::warn!(target: "app", "the aggregated profile \"uid:{uid}\" is not found");
+                continue;
+            };
+            let file = source.file.clone().unwrap_or_default();
+            match dirs::app_profiles_dir().and_then(|dir| help::read_mapping(&dir.join(file))) {
+                Ok(mapping) => mappings.push((source.name.clone().unwrap_or(uid), mapping)),
+                Err(err) => {
+                    log::warn!(target: "app", "failed to read the aggregated profile \"uid:{uid}\", {err}");
+                }
+            }
+        }
+        mappings
+    }
+
+    /// 获取current指向的聚合订阅引用的订阅
+    pub fn current_sources(&self) -> Vec<String> {
+        self.get_current()
+            .and_then(|current| self.get_item(&current).ok())
+            .and_then(|item| item.option.as_ref()?.sources.clone())
+            .unwrap_or_default()
+    }
+
     /// 获取current指向的订阅的merge
     pub fn current_merge(&self) -> Option<String> {
         match (self.current.as_ref(), self.items.as_ref()) {
@@ -465,6 +629,20 @@ impl IProfiles {
         }
     }
 
+    /// 获取current指向的订阅的transform
+    pub fn current_transform(&self) -> Option<String> {
+        match (self.current.as_ref(), self.items.as_ref()) {
+            (Some(current), Some(items)) => {
+                if let Some(item) = items.iter().find(|e| e.uid.as_ref() == Some(current)) {
+                    let transform = item.option.as_ref().and_then(|e| e.transform.clone());
+                    return transform;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
     /// 判断profile是否是current指向的
     pub fn is_current_profile_index(&self, index: String) -> bool {
         self.current == Some(index)
@@ -486,3 +664,20 @@ impl IProfiles {
         })
     }
 }
+
+/// the items that can be the current profile
+fn is_profile(item: &PrfItem) -> bool {
+    matches!(
+        item.itype.as_deref(),
+        Some("remote" | "local" | "aggregate")
+    )
+}
+
+/// keep the fetched data of a remote item in its history
+fn save_version(uid: &str, data: &str) {
+    let limit = Config::verge()
+        .latest()
+        .profile_history_limit
+        .unwrap_or(DEFAULT_PROFILE_HISTORY_LIMIT);
+    log_err!(PrfHistory::new(uid).save(data, limit));
+}
@@ -1,4 +1,4 @@
-use crate::enhance::field::use_keys;
+use crate::enhance::{field::use_keys, lint::Diagnostic};
 use serde::{Deserialize, Serialize};
 use serde_yaml::{Mapping, Value};
 use std::collections::HashMap;
@@ -9,6 +9,8 @@ pub struct IRuntime {
     // 这些keys不一定都生效
     pub exists_keys: Vec<String>,
     pub chain_logs: HashMap<String, Vec<(String, String)>>,
+    // 静态检查最终配置得到的问题
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl IRuntime {
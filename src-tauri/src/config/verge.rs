@@ -6,6 +6,12 @@ use anyhow::Result;
 use log::LevelFilter;
 use serde::{Deserialize, Serialize};
 
+/// 订阅内容默认的最大体积，单位 MB
+pub const DEFAULT_PROFILE_MAX_SIZE: u64 = 20;
+
+/// 每个远程订阅默认保留的历史版本数
+pub const DEFAULT_PROFILE_HISTORY_LIMIT: usize = 10;
+
 /// ### `revilx.yaml` schema
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct IVerge {
@@ -121,6 +127,12 @@ pub struct IVerge {
     /// 默认的延迟测试超时时间
     pub default_latency_timeout: Option<i32>,
 
+    /// 订阅内容的最大体积，单位 MB
+    pub profile_max_size: Option<u64>,
+
+    /// 每个远程订阅保留的历史版本数，0 为不保留
+    pub profile_history_limit: Option<usize>,
+
     /// 是否使用内部的脚本支持，默认为真
     pub enable_builtin_enhanced: Option<bool>,
 
@@ -288,6 +300,8 @@ impl IVerge {
             auto_check_update: Some(true),
             enable_builtin_enhanced: Some(true),
             auto_log_clean: Some(3),
+            profile_max_size: Some(DEFAULT_PROFILE_MAX_SIZE),
+            profile_history_limit: Some(DEFAULT_PROFILE_HISTORY_LIMIT),
             webdav_url: None,
             webdav_username: None,
             webdav_password: None,
@@ -368,6 +382,8 @@ impl IVerge {
         patch!(auto_check_update);
         patch!(default_latency_test);
         patch!(default_latency_timeout);
+        patch!(profile_max_size);
+        patch!(profile_history_limit);
         patch!(enable_builtin_enhanced);
         patch!(proxy_layout_column);
         patch!(test_list);
@@ -446,6 +462,8 @@ pub struct IVergeResponse {
     pub auto_check_update: Option<bool>,
     pub default_latency_test: Option<String>,
     pub default_latency_timeout: Option<i32>,
+    pub profile_max_size: Option<u64>,
+    pub profile_history_limit: Option<usize>,
     pub enable_builtin_enhanced: Option<bool>,
     pub proxy_layout_column: Option<i32>,
     pub test_list: Option<Vec<IVergeTestItem>>,
@@ -511,6 +529,8 @@ impl From<IVerge> for IVergeResponse {
             auto_check_update: verge.auto_check_update,
             default_latency_test: verge.default_latency_test,
             default_latency_timeout: verge.default_latency_timeout,
+            profile_max_size: verge.profile_max_size,
+            profile_history_limit: verge.profile_history_limit,
             enable_builtin_enhanced: verge.enable_builtin_enhanced,
             proxy_layout_column: verge.proxy_layout_column,
             test_list: verge.test_list,
@@ -0,0 +1,109 @@
+//! Render a clash config for clients that cannot read it
+
+use super::{share_link::to_share_link, singbox};
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    /// `outbounds` and `route` of a sing-box config
+    SingBox,
+    /// one share link per line
+    ShareLinks,
+    /// the share links base64 encoded, as most subscriptions are served
+    Base64,
+}
+
+/// An exported config, with what could not be rendered in the format
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Exported {
+    pub data: String,
+    pub warnings: Vec<String>,
+}
+
+/// Export the proxies, groups and rules of `config`
+/// Share links only carry the proxies, groups and rules are dropped
+pub fn export_config(config: &Mapping, format: ExportFormat) -> Result<Exported> {
+    let mut warnings = Vec::new();
+    if config
+        .get("proxy-providers")
+        .and_then(Value::as_mapping)
+        .is_some_and(|providers| !providers.is_empty())
+    {
+        warnings.push("proxies of proxy providers are not exported".to_string());
+    }
+
+    let data = match format {
+        ExportFormat::SingBox => {
+            let (json, mut sing_box_warnings) = singbox::export(config);
+            warnings.append(&mut sing_box_warnings);
+            serde_json::to_string_pretty(&json)?
+        }
+        ExportFormat::ShareLinks | ExportFormat::Base64 => {
+            let mut links = Vec::new();
+            for proxy in proxies(config) {
+                let name = proxy
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                match to_share_link(proxy) {
+                    Ok(link) => links.push(link),
+                    Err(err) => warnings.push(format!("proxy `{name}`: {err}, skipped")),
+                }
+            }
+            let links = links.join("\n");
+            match format {
+                ExportFormat::Base64 => STANDARD.encode(links),
+                _ => links,
+            }
+        }
+    };
+
+    Ok(Exported { data, warnings })
+}
+
+fn proxies(config: &Mapping) -> impl Iterator<Item = &Mapping> {
+    config
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_mapping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::{decode_base64, parse_share_links};
+
+    const CONFIG: &str = r#"
+proxies:
+  - {name: hk, type: ss, server: hk.example.com, port: 8388, cipher: aes-128-gcm, password: pass}
+  - {name: jp, type: trojan, server: jp.example.com, port: 443, password: pass, sni: jp.example.com}
+  - {name: wg, type: wireguard, server: wg.example.com, port: 51820}
+proxy-groups:
+  - {name: Proxy, type: select, proxies: [hk, jp, wg, DIRECT]}
+rules:
+  - DOMAIN-SUFFIX,cn,DIRECT
+  - MATCH,Proxy
+"#;
+
+    #[test]
+    fn test_export_share_links() {
+        let config: Mapping = serde_yaml::from_str(CONFIG).unwrap();
+        let exported = export_config(&config, ExportFormat::Base64).unwrap();
+        assert_eq!(exported.warnings.len(), 1, "{:?}", exported.warnings);
+
+        let links = decode_base64(&exported.data).unwrap();
+        let (proxies, warnings) = parse_share_links(&links);
+        assert!(warnings.is_empty());
+        let names: Vec<&str> = proxies
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["hk", "jp"]);
+    }
+}
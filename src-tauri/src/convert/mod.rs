@@ -0,0 +1,466 @@
+mod export;
+mod quantumult;
+mod share_link;
+mod singbox;
+mod surge;
+
+pub use self::export::*;
+pub use self::share_link::*;
+
+use crate::utils::rule::rule_target;
+use anyhow::{bail, Context, Result};
+use serde_yaml::{Mapping, Sequence, Value};
+use std::collections::HashSet;
+
+/// name of the group generated for converted subscriptions
+pub const DEFAULT_GROUP: &str = "PROXY";
+
+/// A subscription turned into a clash profile, with what could not be converted
+#[derive(Debug, Clone, Default)]
+pub struct Converted {
+    /// where it came from, `sing-box`, `surge`, `quantumult-x` or `share-links`
+    pub format: &'static str,
+    pub config: Mapping,
+    pub warnings: Vec<String>,
+}
+
+/// Check a downloaded subscription and convert it when it is not a clash yaml
+/// `Ok(None)` when `data` can be used as it is
+pub fn convert_subscription(data: &str) -> Result<Option<Converted>> {
+    let yaml_err = match serde_yaml::from_str::<Mapping>(data) {
+        Ok(yaml) if yaml.contains_key("proxies") || yaml.contains_key("proxy-providers") => {
+            return Ok(None);
+        }
+        Ok(_) => None,
+        Err(err) => Some(err),
+    };
+
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
+        if json
+            .get("outbounds")
+            .is_some_and(serde_json::Value::is_array)
+        {
+            return singbox::convert(&json).map(Some);
+        }
+    }
+
+    let sections = ini_sections(data);
+    if sections.iter().any(|(name, _)| name == "proxy") {
+        return surge::convert(&sections).map(Some);
+    }
+    if sections.iter().any(|(name, _)| name == "server_local") {
+        return quantumult::convert(&sections).map(Some);
+    }
+
+    let (proxies, warnings) = parse_share_links(data);
+    if !proxies.is_empty() {
+        let profile = Profile {
+            proxies,
+            warnings,
+            ..Profile::default()
+        };
+        return profile.build("share-links").map(Some);
+    }
+
+    // a bare `server_remote` resource of quantumult x
+    if let Ok(converted) = quantumult::convert(&[("server_local".into(), lines(data))]) {
+        return Ok(Some(converted));
+    }
+
+    match yaml_err {
+        Some(err) => Err(err).context("the remote profile data is invalid yaml"),
+        None => bail!("profile does not contain `proxies` or `proxy-providers`"),
+    }
+}
+
+/// What a foreign config translates to, before it is checked and assembled
+#[derive(Debug, Default)]
+struct Profile {
+    proxies: Vec<Mapping>,
+    groups: Vec<Mapping>,
+    /// `TYPE,payload,target[,options]`
+    rules: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl Profile {
+    /// Assemble the profile the core will accept
+    ///
+    /// Duplicate proxy names get a numeric suffix, group members and rule targets
+    /// that do not exist are dropped with a warning. Without groups all proxies
+    /// go into one select group, without rules private networks go direct and
+    /// everything else through the first group.
+    fn build(mut self, format: &'static str) -> Result<Converted> {
+        if self.proxies.is_empty() {
+            bail!("no proxy could be converted from the {format} config");
+        }
+
+        let mut seen = HashSet::new();
+        for proxy in self.proxies.iter_mut() {
+            let name = proxy
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let mut unique = name.clone();
+            let mut index = 2;
+            while !seen.insert(unique.clone()) {
+                unique = format!("{name} {index}");
+                index += 1;
+            }
+            if unique != name {
+                proxy.insert("name".into(), unique.into());
+            }
+        }
+
+        if self.groups.is_empty() {
+            let mut group = Mapping::new();
+            set(&mut group, "name", DEFAULT_GROUP);
+            set(&mut group, "type", "select");
+            let mut names: Vec<String> = self.proxy_names().map(str::to_string).collect();
+            names.push("DIRECT".into());
+            set(&mut group, "proxies", names);
+            self.groups.push(group);
+        }
+
+        let group_names: Vec<String> = self.group_names().map(str::to_string).collect();
+        let known: HashSet<String> = self
+            .proxy_names()
+            .map(str::to_string)
+            .chain(group_names.iter().cloned())
+            .chain(["DIRECT", "REJECT", "REJECT-DROP", "PASS"].map(String::from))
+            .collect();
+
+        for group in self.groups.iter_mut() {
+            let name = group["name"].as_str().unwrap_or_default().to_string();
+            let Some(Value::Sequence(members)) = group.get_mut("proxies") else {
+                continue;
+            };
+            members.retain(|member| {
+                let member = member.as_str().unwrap_or_default();
+                let keep = known.contains(member) && member != name;
+                if !keep {
+                    self.warnings
+                        .push(format!("group `{name}`: dropped unknown member `{member}`"));
+                }
+                keep
+            });
+            if members.is_empty() && !group.contains_key("use") {
+                set(group, "proxies", vec!["DIRECT"]);
+            }
+        }
+
+        let mut rules = Sequence::new();
+        for rule in std::mem::take(&mut self.rules) {
+            match rule_target(&rule) {
+                Some((_, target)) if known.contains(target) => rules.push(rule.into()),
+                _ => self
+                    .warnings
+                    .push(format!("rule `{rule}`: unknown target, skipped")),
+            }
+        }
+        if rules.is_empty() {
+            for rule in DEFAULT_RULES {
+                rules.push(Value::from(*rule));
+            }
+        }
+        let has_match = rules
+            .last()
+            .and_then(Value::as_str)
+            .is_some_and(|rule| rule.starts_with("MATCH,"));
+        if !has_match {
+            rules.push(format!("MATCH,{}", group_names[0]).into());
+        }
+
+        let mut config = Mapping::new();
+        set(&mut config, "proxies", self.proxies);
+        set(&mut config, "proxy-groups", self.groups);
+        set(&mut config, "rules", rules);
+        Ok(Converted {
+            format,
+            config,
+            warnings: self.warnings,
+        })
+    }
+
+    fn proxy_names(&self) -> impl Iterator<Item = &str> {
+        self.proxies.iter().filter_map(|p| p.get("name")?.as_str())
+    }
+
+    fn group_names(&self) -> impl Iterator<Item = &str> {
+        self.groups.iter().filter_map(|g| g.get("name")?.as_str())
+    }
+}
+
+/// private networks go direct, the final `MATCH` is added by `Profile::build`
+const DEFAULT_RULES: &[&str] = &[
+    "DOMAIN-SUFFIX,local,DIRECT",
+    "IP-CIDR,127.0.0.0/8,DIRECT,no-resolve",
+    "IP-CIDR,10.0.0.0/8,DIRECT,no-resolve",
+    "IP-CIDR,172.16.0.0/12,DIRECT,no-resolve",
+    "IP-CIDR,192.168.0.0/16,DIRECT,no-resolve",
+];
+
+/// `[Section]` headers (lowercased) with their non-empty, non-comment lines
+fn ini_sections(data: &str) -> Vec<(String, Vec<String>)> {
+    let mut sections: Vec<(String, Vec<String>)> = Vec::new();
+    for line in lines(data) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_lowercase(), Vec::new()));
+        } else if let Some((_, section)) = sections.last_mut() {
+            section.push(line);
+        }
+    }
+    sections
+}
+
+/// lines of every section called `name`
+fn section<'a>(
+    sections: &'a [(String, Vec<String>)],
+    name: &'a str,
+) -> impl Iterator<Item = &'a String> {
+    sections
+        .iter()
+        .filter(move |(section, _)| section == name)
+        .flat_map(|(_, lines)| lines.iter())
+}
+
+fn lines(data: &str) -> Vec<String> {
+    data.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter(|line| !line.starts_with('#') && !line.starts_with(';') && !line.starts_with("//"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// `key=value` options after the positional fields of a surge or quantumult x line
+fn key_values<'a>(fields: impl Iterator<Item = &'a str>) -> Vec<(String, String)> {
+    fields
+        .filter_map(|field| field.split_once('='))
+        .map(|(key, value)| (key.trim().to_lowercase(), value.trim().to_string()))
+        .collect()
+}
+
+fn lookup<'a>(options: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+/// the `network` of a proxy and its `*-opts`
+pub(super) fn set_transport(
+    proxy: &mut Mapping,
+    network: &str,
+    host: Option<&str>,
+    path: Option<&str>,
+    service_name: Option<&str>,
+) -> Result<()> {
+    let mut opts = Mapping::new();
+    match network {
+        "tcp" | "" => return Ok(()),
+        "ws" | "httpupgrade" => {
+            set_opt(&mut opts, "path", path);
+            if let Some(host) = host {
+                let mut headers = Mapping::new();
+                set(&mut headers, "Host", host);
+                set(&mut opts, "headers", headers);
+            }
+            if network == "httpupgrade" {
+                set(&mut opts, "v2ray-http-upgrade", true);
+            }
+            set(proxy, "network", "ws");
+            set(proxy, "ws-opts", opts);
+        }
+        "grpc" => {
+            set_opt(&mut opts, "grpc-service-name", service_name);
+            set(proxy, "network", "grpc");
+            set(proxy, "grpc-opts", opts);
+        }
+        "h2" => {
+            set_opt(&mut opts, "host", host.map(split_list));
+            set_opt(&mut opts, "path", path);
+            set(proxy, "network", "h2");
+            set(proxy, "h2-opts", opts);
+        }
+        "http" => {
+            set_opt(&mut opts, "path", path.map(split_list));
+            if let Some(host) = host {
+                let mut headers = Mapping::new();
+                set(&mut headers, "Host", split_list(host));
+                set(&mut opts, "headers", headers);
+            }
+            set(proxy, "network", "http");
+            set(proxy, "http-opts", opts);
+        }
+        network => bail!("unsupported transport `{network}`"),
+    }
+    Ok(())
+}
+
+/// The transport of a clash proxy, the reverse of `set_transport`
+/// `http` is the plain http header obfuscation, `h2` is http/2 over tls
+#[derive(Debug, Default)]
+struct Transport<'a> {
+    network: &'a str,
+    host: Option<String>,
+    path: Option<String>,
+    service_name: Option<String>,
+    /// `ws-opts.v2ray-http-upgrade`
+    http_upgrade: bool,
+}
+
+fn get_transport(proxy: &Mapping) -> Transport<'_> {
+    let network = proxy
+        .get("network")
+        .and_then(Value::as_str)
+        .unwrap_or("tcp");
+    let opts = |key: &str| proxy.get(key).and_then(Value::as_mapping);
+    // a string or the first one of a list
+    let first = |value: Option<&Value>| match value? {
+        Value::Sequence(values) => values.first().and_then(Value::as_str).map(str::to_string),
+        value => value.as_str().map(str::to_string),
+    };
+    let mut transport = Transport {
+        network,
+        ..Transport::default()
+    };
+    match network {
+        "ws" => {
+            let Some(opts) = opts("ws-opts") else {
+                return transport;
+            };
+            transport.path = first(opts.get("path"));
+            transport.host = first(opts.get("headers").and_then(|h| h.get("Host")));
+            transport.http_upgrade = opts
+                .get("v2ray-http-upgrade")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+        }
+        "grpc" => {
+            let opts = opts("grpc-opts");
+            transport.service_name = first(opts.and_then(|o| o.get("grpc-service-name")));
+        }
+        "h2" => {
+            let opts = opts("h2-opts");
+            transport.host = first(opts.and_then(|o| o.get("host")));
+            transport.path = first(opts.and_then(|o| o.get("path")));
+        }
+        "http" => {
+            let opts = opts("http-opts");
+            transport.path = first(opts.and_then(|o| o.get("path")));
+            let headers = opts.and_then(|o| o.get("headers"));
+            transport.host = first(headers.and_then(|h| h.get("Host")));
+        }
+        _ => {}
+    }
+    transport
+}
+
+/// a string, number or bool field as a string
+fn field(map: &Mapping, key: &str) -> Option<String> {
+    match map.get(key)? {
+        Value::String(value) if !value.is_empty() => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn set(map: &mut Mapping, key: &str, value: impl Into<Value>) {
+    map.insert(key.into(), value.into());
+}
+
+/// only set when present and not empty
+fn set_opt<T: Into<Value>>(map: &mut Mapping, key: &str, value: Option<T>) {
+    if let Some(value) = value.map(Into::into) {
+        if value.as_str().is_some_and(str::is_empty) {
+            return;
+        }
+        map.insert(key.into(), value);
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+fn is_true(value: &str) -> bool {
+    matches!(value, "1" | "true")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_clash_yaml() {
+        let yaml = "proxies:\n- {name: a, type: ss}\n";
+        assert!(convert_subscription(yaml).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_convert_share_links() {
+        let links = "trojan://pass@a.example.com:443#node\ntrojan://pass@b.example.com:443#node\n";
+        let converted = convert_subscription(links).unwrap().unwrap();
+        assert_eq!(converted.format, "share-links");
+        let config = converted.config;
+
+        let names: Vec<&str> = config["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["node", "node 2"]);
+        assert_eq!(
+            config["proxy-groups"][0]["proxies"],
+            serde_yaml::from_str::<Value>("[node, node 2, DIRECT]").unwrap()
+        );
+        let rules = config["rules"].as_sequence().unwrap();
+        assert_eq!(rules.last().unwrap(), "MATCH,PROXY");
+    }
+
+    #[test]
+    fn test_drop_unknown_references() {
+        let mut proxy = Mapping::new();
+        set(&mut proxy, "name", "a");
+        let mut group = Mapping::new();
+        set(&mut group, "name", "G");
+        set(&mut group, "type", "select");
+        set(&mut group, "proxies", vec!["a", "missing"]);
+        let profile = Profile {
+            proxies: vec![proxy],
+            groups: vec![group],
+            rules: vec!["DOMAIN,a.com,G".into(), "DOMAIN,b.com,nowhere".into()],
+            warnings: vec![],
+        };
+        let converted = profile.build("test").unwrap();
+        let config = converted.config;
+        assert_eq!(
+            config["proxy-groups"][0]["proxies"]
+                .as_sequence()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            config["rules"],
+            serde_yaml::from_str::<Value>("[\"DOMAIN,a.com,G\", \"MATCH,G\"]").unwrap()
+        );
+        assert_eq!(converted.warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_reject_unknown_data() {
+        let err = convert_subscription("<html>login</html>").unwrap_err();
+        assert!(err.to_string().contains("invalid yaml"));
+        let err = convert_subscription("mixed-port: 7890").unwrap_err();
+        assert!(err.to_string().contains("does not contain"));
+    }
+}
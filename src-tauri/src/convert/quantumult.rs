@@ -0,0 +1,337 @@
+//! Quantumult X configs: `[server_local]`, `[policy]` and `[filter_local]`
+
+use super::{
+    is_true, key_values, lookup, section, set, set_opt, set_transport, Converted, Profile,
+    DEFAULT_GROUP,
+};
+use anyhow::{bail, Context, Result};
+use serde_yaml::{Mapping, Value};
+
+pub(super) fn convert(sections: &[(String, Vec<String>)]) -> Result<Converted> {
+    let mut profile = Profile::default();
+
+    for line in section(sections, "server_local") {
+        match convert_server(line) {
+            Ok(proxy) => profile.proxies.push(proxy),
+            Err(err) => profile
+                .warnings
+                .push(format!("server `{line}`: {err}, skipped")),
+        }
+    }
+
+    for line in section(sections, "policy") {
+        let Some((kind, value)) = line.split_once('=') else {
+            continue;
+        };
+        let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+        let name = fields[0];
+        let kind = match kind.trim() {
+            "static" => "select",
+            "url-latency-benchmark" => "url-test",
+            "available" => "fallback",
+            "round-robin" => "load-balance",
+            kind => {
+                profile.warnings.push(format!(
+                    "policy `{name}`: unsupported type `{kind}`, skipped"
+                ));
+                continue;
+            }
+        };
+        let members: Vec<&str> = fields[1..]
+            .iter()
+            .copied()
+            .filter(|field| !field.contains('='))
+            .collect();
+        let options = key_values(fields[1..].iter().copied());
+        if lookup(&options, "server-tag-regex").is_some()
+            || lookup(&options, "resource-tag-regex").is_some()
+        {
+            profile.warnings.push(format!(
+                "policy `{name}`: tag regex filters are not supported"
+            ));
+        }
+
+        let mut group = Mapping::new();
+        set(&mut group, "name", name);
+        set(&mut group, "type", kind);
+        set(
+            &mut group,
+            "proxies",
+            members.iter().map(|m| m.to_string()).collect::<Vec<_>>(),
+        );
+        if kind != "select" {
+            set(&mut group, "url", "http://www.gstatic.com/generate_204");
+            let interval = lookup(&options, "check-interval").and_then(|i| i.parse::<u64>().ok());
+            set(&mut group, "interval", interval.unwrap_or(600));
+            set_opt(
+                &mut group,
+                "tolerance",
+                lookup(&options, "tolerance").and_then(|t| t.parse::<u64>().ok()),
+            );
+        }
+        profile.groups.push(group);
+    }
+
+    // the builtin `proxy` policy stands for the main group
+    let main_group = profile
+        .groups
+        .first()
+        .and_then(|group| group.get("name")?.as_str())
+        .unwrap_or(DEFAULT_GROUP)
+        .to_string();
+    let policy = |name: &str| match name.to_lowercase().as_str() {
+        "direct" => "DIRECT".to_string(),
+        "reject" | "reject-img" | "reject-tinygif" | "reject-dict" | "reject-array"
+        | "reject-200" => "REJECT".to_string(),
+        "proxy" => main_group.clone(),
+        _ => name.to_string(),
+    };
+    for group in profile.groups.iter_mut() {
+        if let Some(Value::Sequence(members)) = group.get_mut("proxies") {
+            for member in members.iter_mut() {
+                *member = policy(member.as_str().unwrap_or_default()).into();
+            }
+        }
+    }
+
+    for line in section(sections, "filter_local") {
+        match convert_filter(line, policy) {
+            Ok(rule) => profile.rules.push(rule),
+            Err(err) => profile
+                .warnings
+                .push(format!("filter `{line}`: {err}, skipped")),
+        }
+    }
+
+    profile.build("quantumult-x")
+}
+
+/// `type=host:port, key=value, ..., tag=Name`
+fn convert_server(line: &str) -> Result<Mapping> {
+    let (kind, value) = line.split_once('=').context("missing type")?;
+    let kind = kind.trim().to_lowercase();
+    let mut fields = value.split(',').map(str::trim);
+    let address = fields.next().context("missing server")?;
+    let (server, port) = address.rsplit_once(':').context("missing port")?;
+    let server = server.trim_start_matches('[').trim_end_matches(']');
+    let port: u16 = port.parse().context("invalid port")?;
+    let options = key_values(fields);
+    let option = |key: &str| lookup(&options, key);
+
+    let mut proxy = Mapping::new();
+    set(
+        &mut proxy,
+        "name",
+        option("tag").map_or_else(|| address.to_string(), str::to_string),
+    );
+    match kind.as_str() {
+        "shadowsocks" => {
+            set(&mut proxy, "type", "ss");
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set(
+                &mut proxy,
+                "cipher",
+                option("method").context("missing method")?,
+            );
+            set(
+                &mut proxy,
+                "password",
+                option("password").context("missing password")?,
+            );
+            set(&mut proxy, "udp", option("udp-relay").is_some_and(is_true));
+            match option("obfs") {
+                Some(mode @ ("http" | "tls")) => {
+                    let mut opts = Mapping::new();
+                    set(&mut opts, "mode", mode);
+                    set_opt(&mut opts, "host", option("obfs-host"));
+                    set(&mut proxy, "plugin", "obfs");
+                    set(&mut proxy, "plugin-opts", opts);
+                }
+                Some(mode @ ("ws" | "wss")) => {
+                    let mut opts = Mapping::new();
+                    set(&mut opts, "mode", "websocket");
+                    set_opt(&mut opts, "host", option("obfs-host"));
+                    set_opt(&mut opts, "path", option("obfs-uri"));
+                    set(&mut opts, "tls", mode == "wss");
+                    set(&mut proxy, "plugin", "v2ray-plugin");
+                    set(&mut proxy, "plugin-opts", opts);
+                }
+                Some(mode) => bail!("unsupported obfs `{mode}`"),
+                None => {}
+            }
+            return Ok(proxy);
+        }
+        "vmess" | "vless" => {
+            set(&mut proxy, "type", kind.as_str());
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set(
+                &mut proxy,
+                "uuid",
+                option("password").context("missing password")?,
+            );
+            if kind == "vmess" {
+                set(&mut proxy, "alterId", 0);
+                set(&mut proxy, "cipher", option("method").unwrap_or("auto"));
+            }
+            set(&mut proxy, "udp", true);
+        }
+        "trojan" => {
+            set(&mut proxy, "type", "trojan");
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set(
+                &mut proxy,
+                "password",
+                option("password").context("missing password")?,
+            );
+            set(&mut proxy, "udp", option("udp-relay").is_some_and(is_true));
+        }
+        "http" | "socks5" => {
+            set(&mut proxy, "type", kind.as_str());
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set_opt(&mut proxy, "username", option("username"));
+            set_opt(&mut proxy, "password", option("password"));
+        }
+        kind => bail!("unsupported type `{kind}`"),
+    }
+
+    // tls and transport of vmess, vless, trojan, http and socks5
+    let sni_key = if kind == "trojan" || kind == "http" || kind == "socks5" {
+        "sni"
+    } else {
+        "servername"
+    };
+    match option("obfs") {
+        Some("over-tls") => {
+            set(&mut proxy, "tls", true);
+            set_opt(&mut proxy, sni_key, option("obfs-host"));
+        }
+        Some(mode @ ("ws" | "wss")) => {
+            set_transport(
+                &mut proxy,
+                "ws",
+                option("obfs-host"),
+                option("obfs-uri"),
+                None,
+            )?;
+            if mode == "wss" {
+                set(&mut proxy, "tls", true);
+                set_opt(&mut proxy, sni_key, option("obfs-host"));
+            }
+        }
+        Some(mode) => bail!("unsupported obfs `{mode}`"),
+        None => {}
+    }
+    if option("over-tls").is_some_and(is_true) {
+        set(&mut proxy, "tls", true);
+        set_opt(&mut proxy, sni_key, option("tls-host"));
+    }
+    if option("tls-verification").is_some_and(|verify| !is_true(verify)) {
+        set(&mut proxy, "skip-cert-verify", true);
+    }
+    if kind == "trojan" && !proxy.contains_key("sni") {
+        set_opt(&mut proxy, "sni", option("tls-host"));
+    }
+
+    Ok(proxy)
+}
+
+/// `type, payload, policy`
+fn convert_filter(line: &str, policy: impl Fn(&str) -> String) -> Result<String> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    let kind = fields[0].to_lowercase();
+    if kind == "final" {
+        let target = fields.get(1).context("missing policy")?;
+        return Ok(format!("MATCH,{}", policy(target)));
+    }
+
+    let kind = match kind.as_str() {
+        "host" => "DOMAIN",
+        "host-suffix" => "DOMAIN-SUFFIX",
+        "host-keyword" => "DOMAIN-KEYWORD",
+        "ip-cidr" => "IP-CIDR",
+        "ip6-cidr" => "IP-CIDR6",
+        "geoip" => "GEOIP",
+        "ip-asn" => "IP-ASN",
+        kind => bail!("unsupported type `{kind}`"),
+    };
+    let payload = fields.get(1).context("missing payload")?;
+    let target = fields.get(2).context("missing policy")?;
+    let mut rule = format!("{kind},{payload},{}", policy(target));
+    if fields[3..].contains(&"no-resolve") {
+        rule.push_str(",no-resolve");
+    }
+    Ok(rule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ini_sections;
+    use super::*;
+
+    const CONFIG: &str = r#"
+[general]
+network_check_url=http://www.gstatic.com/generate_204
+
+[server_local]
+shadowsocks=hk.example.com:8388, method=chacha20-ietf-poly1305, password=pass, obfs=http, obfs-host=bing.com, udp-relay=true, tag=HK
+vmess=jp.example.com:443, method=chacha20-poly1305, password=b831381d-6324-4d53-ad4f-8cda48b30811, obfs=wss, obfs-host=cdn.example.com, obfs-uri=/v2, tag=JP
+trojan=us.example.com:443, password=pass, over-tls=true, tls-host=us.example.com, tls-verification=false, tag=US
+shadowsocks=sg.example.com:8388, method=aes-128-gcm, password=pass, obfs=quic, tag=SG
+
+[policy]
+static=Proxy, Auto, HK, JP, US, direct
+url-latency-benchmark=Auto, HK, JP, check-interval=300, tolerance=50
+available=Backup, server-tag-regex=^US, US
+
+[filter_local]
+host-suffix, example.cn, direct
+ip-cidr, 10.0.0.0/8, direct, no-resolve
+user-agent, Telegram*, proxy
+host, ads.example.com, reject-img
+final, proxy
+"#;
+
+    #[test]
+    fn test_convert_quantumult() {
+        let converted = convert(&ini_sections(CONFIG)).unwrap();
+        let config = converted.config;
+
+        let proxies = config["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 3);
+        assert_eq!(proxies[0]["plugin"], "obfs");
+        assert_eq!(proxies[1]["network"], "ws");
+        assert_eq!(proxies[1]["ws-opts"]["path"], "/v2");
+        assert_eq!(proxies[1]["tls"].as_bool(), Some(true));
+        assert_eq!(proxies[2]["sni"], "us.example.com");
+        assert_eq!(proxies[2]["skip-cert-verify"].as_bool(), Some(true));
+
+        let groups = config["proxy-groups"].as_sequence().unwrap();
+        assert_eq!(groups[0]["proxies"][4], "DIRECT");
+        assert_eq!(groups[1]["type"], "url-test");
+        assert_eq!(groups[1]["interval"], 300);
+        assert_eq!(groups[2]["type"], "fallback");
+
+        let rules: Vec<&str> = config["rules"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|r| r.as_str().unwrap())
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                "DOMAIN-SUFFIX,example.cn,DIRECT",
+                "IP-CIDR,10.0.0.0/8,DIRECT,no-resolve",
+                "DOMAIN,ads.example.com,REJECT",
+                "MATCH,Proxy",
+            ]
+        );
+
+        // the quic obfs, the tag regex and the user agent filter
+        assert_eq!(converted.warnings.len(), 3, "{:?}", converted.warnings);
+    }
+}
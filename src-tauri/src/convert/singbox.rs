@@ -0,0 +1,806 @@
+//! sing-box configs: `outbounds` become proxies and groups, `route.rules` basic rules
+//! and the other way round for exporting
+
+use super::{
+    field, get_transport, set, set_opt, set_transport,
+    share_link::{set_ss_plugin, ss_plugin},
+    Converted, Profile,
+};
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value as Json};
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+
+pub(super) fn convert(json: &Json) -> Result<Converted> {
+    let mut profile = Profile::default();
+    // `direct` and `block` outbounds are referred to by their tag
+    let mut builtins = HashMap::new();
+
+    let outbounds = json["outbounds"].as_array().context("missing outbounds")?;
+    for outbound in outbounds {
+        let tag = str_of(outbound, "tag").unwrap_or_default();
+        let kind = str_of(outbound, "type").unwrap_or_default();
+        match kind {
+            "direct" => {
+                builtins.insert(tag.to_string(), "DIRECT");
+            }
+            "block" => {
+                builtins.insert(tag.to_string(), "REJECT");
+            }
+            "dns" => {}
+            "selector" | "urltest" => {}
+            _ => match convert_outbound(outbound) {
+                Ok(proxy) => profile.proxies.push(proxy),
+                Err(err) => profile
+                    .warnings
+                    .push(format!("outbound `{tag}`: {err}, skipped")),
+            },
+        }
+    }
+
+    let target = |tag: &str| builtins.get(tag).map_or(tag.to_string(), |b| b.to_string());
+    for outbound in outbounds {
+        let kind = str_of(outbound, "type").unwrap_or_default();
+        if kind != "selector" && kind != "urltest" {
+            continue;
+        }
+        let mut group = Mapping::new();
+        set(
+            &mut group,
+            "name",
+            str_of(outbound, "tag").unwrap_or_default(),
+        );
+        let members: Vec<String> = outbound["outbounds"]
+            .as_array()
+            .map(|tags| tags.iter().filter_map(Json::as_str).map(target).collect())
+            .unwrap_or_default();
+        if kind == "selector" {
+            set(&mut group, "type", "select");
+            // the core starts a select group on its first member
+            let default = str_of(outbound, "default").map(target);
+            let mut members = members;
+            if let Some(index) = default.and_then(|d| members.iter().position(|m| *m == d)) {
+                let member = members.remove(index);
+                members.insert(0, member);
+            }
+            set(&mut group, "proxies", members);
+        } else {
+            set(&mut group, "type", "url-test");
+            set(&mut group, "proxies", members);
+            set_opt(&mut group, "url", str_of(outbound, "url"));
+            let interval = str_of(outbound, "interval").and_then(parse_duration);
+            set(&mut group, "interval", interval.unwrap_or(180));
+            set_opt(&mut group, "tolerance", outbound["tolerance"].as_u64());
+        }
+        profile.groups.push(group);
+    }
+
+    let route = &json["route"];
+    for rule in route["rules"].as_array().into_iter().flatten() {
+        match convert_rule(rule, target) {
+            Ok(rules) => profile.rules.extend(rules),
+            Err(err) => profile
+                .warnings
+                .push(format!("route rule `{rule}`: {err}, skipped")),
+        }
+    }
+    if let Some(last) = str_of(route, "final") {
+        profile.rules.push(format!("MATCH,{}", target(last)));
+    }
+
+    profile.build("sing-box")
+}
+
+fn convert_outbound(outbound: &Json) -> Result<Mapping> {
+    let kind = str_of(outbound, "type").unwrap_or_default();
+    let mut proxy = Mapping::new();
+    set(
+        &mut proxy,
+        "name",
+        str_of(outbound, "tag").context("missing tag")?,
+    );
+    let server = str_of(outbound, "server").context("missing server")?;
+    let port = outbound["server_port"]
+        .as_u64()
+        .context("missing server_port")?;
+
+    match kind {
+        "shadowsocks" => {
+            set(&mut proxy, "type", "ss");
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set(&mut proxy, "cipher", required(outbound, "method")?);
+            set(&mut proxy, "password", required(outbound, "password")?);
+            set(&mut proxy, "udp", true);
+            if let Some(plugin) = str_of(outbound, "plugin") {
+                let options = str_of(outbound, "plugin_opts").unwrap_or_default();
+                set_ss_plugin(&mut proxy, plugin, options)?;
+            }
+        }
+        "vmess" => {
+            set(&mut proxy, "type", "vmess");
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set(&mut proxy, "uuid", required(outbound, "uuid")?);
+            set(
+                &mut proxy,
+                "alterId",
+                outbound["alter_id"].as_u64().unwrap_or(0),
+            );
+            set(
+                &mut proxy,
+                "cipher",
+                str_of(outbound, "security").unwrap_or("auto"),
+            );
+            set(&mut proxy, "udp", true);
+        }
+        "vless" => {
+            set(&mut proxy, "type", "vless");
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set(&mut proxy, "uuid", required(outbound, "uuid")?);
+            set_opt(&mut proxy, "flow", str_of(outbound, "flow"));
+            set(&mut proxy, "udp", true);
+        }
+        "trojan" => {
+            set(&mut proxy, "type", "trojan");
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set(&mut proxy, "password", required(outbound, "password")?);
+            set(&mut proxy, "udp", true);
+        }
+        "hysteria2" => {
+            set(&mut proxy, "type", "hysteria2");
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set_opt(&mut proxy, "password", str_of(outbound, "password"));
+            let obfs = &outbound["obfs"];
+            set_opt(&mut proxy, "obfs", str_of(obfs, "type"));
+            set_opt(&mut proxy, "obfs-password", str_of(obfs, "password"));
+            set_opt(&mut proxy, "up", outbound["up_mbps"].as_u64());
+            set_opt(&mut proxy, "down", outbound["down_mbps"].as_u64());
+        }
+        "tuic" => {
+            set(&mut proxy, "type", "tuic");
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set(&mut proxy, "uuid", required(outbound, "uuid")?);
+            set_opt(&mut proxy, "password", str_of(outbound, "password"));
+            set_opt(
+                &mut proxy,
+                "congestion-controller",
+                str_of(outbound, "congestion_control"),
+            );
+            set_opt(
+                &mut proxy,
+                "udp-relay-mode",
+                str_of(outbound, "udp_relay_mode"),
+            );
+            set(&mut proxy, "udp", true);
+        }
+        "socks" => {
+            if str_of(outbound, "version").is_some_and(|v| v != "5") {
+                bail!("only socks5 is supported");
+            }
+            set(&mut proxy, "type", "socks5");
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set_opt(&mut proxy, "username", str_of(outbound, "username"));
+            set_opt(&mut proxy, "password", str_of(outbound, "password"));
+            set(&mut proxy, "udp", true);
+        }
+        "http" => {
+            set(&mut proxy, "type", "http");
+            set(&mut proxy, "server", server);
+            set(&mut proxy, "port", port);
+            set_opt(&mut proxy, "username", str_of(outbound, "username"));
+            set_opt(&mut proxy, "password", str_of(outbound, "password"));
+        }
+        kind => bail!("unsupported type `{kind}`"),
+    }
+
+    set_tls(&mut proxy, kind, &outbound["tls"]);
+    let transport = &outbound["transport"];
+    if let Some(network) = str_of(transport, "type") {
+        // the http transport of sing-box is h2 over tls
+        let network = match network {
+            "http" if outbound["tls"]["enabled"].as_bool() == Some(true) => "h2",
+            "quic" => bail!("unsupported transport `quic`"),
+            network => network,
+        };
+        let host = match &transport["host"] {
+            Json::Array(hosts) => hosts.first().and_then(Json::as_str),
+            host => host.as_str(),
+        };
+        let host = host.or_else(|| str_of(&transport["headers"], "Host"));
+        set_transport(
+            &mut proxy,
+            network,
+            host,
+            str_of(transport, "path"),
+            str_of(transport, "service_name"),
+        )?;
+        if let Some(early_data) = transport["max_early_data"].as_u64() {
+            if let Some(serde_yaml::Value::Mapping(opts)) = proxy.get_mut("ws-opts") {
+                set(opts, "max-early-data", early_data);
+                set_opt(
+                    opts,
+                    "early-data-header-name",
+                    str_of(transport, "early_data_header_name"),
+                );
+            }
+        }
+    }
+
+    Ok(proxy)
+}
+
+fn set_tls(proxy: &mut Mapping, kind: &str, tls: &Json) {
+    if tls["enabled"].as_bool() != Some(true) {
+        return;
+    }
+    let sni_key = match kind {
+        "vmess" | "vless" => {
+            set(proxy, "tls", true);
+            "servername"
+        }
+        "http" | "socks" => {
+            set(proxy, "tls", true);
+            "sni"
+        }
+        _ => "sni",
+    };
+    set_opt(proxy, sni_key, str_of(tls, "server_name"));
+    if tls["insecure"].as_bool() == Some(true) {
+        set(proxy, "skip-cert-verify", true);
+    }
+    let alpn: Vec<&str> = match &tls["alpn"] {
+        Json::Array(alpn) => alpn.iter().filter_map(Json::as_str).collect(),
+        Json::String(alpn) => vec![alpn.as_str()],
+        _ => vec![],
+    };
+    if !alpn.is_empty() {
+        set(proxy, "alpn", alpn);
+    }
+    let utls = &tls["utls"];
+    if utls["enabled"].as_bool() == Some(true) {
+        set_opt(proxy, "client-fingerprint", str_of(utls, "fingerprint"));
+    }
+    let reality = &tls["reality"];
+    if reality["enabled"].as_bool() == Some(true) {
+        let mut opts = Mapping::new();
+        set_opt(&mut opts, "public-key", str_of(reality, "public_key"));
+        set_opt(&mut opts, "short-id", str_of(reality, "short_id"));
+        set(proxy, "reality-opts", opts);
+    }
+}
+
+/// The fields of a sing-box rule are or-ed within the domain and ip family,
+/// which maps to one clash rule per value. Rules that and several families
+/// together or match on anything else have no plain clash equivalent.
+fn convert_rule(rule: &Json, target: impl Fn(&str) -> String) -> Result<Vec<String>> {
+    let Json::Object(fields) = rule else {
+        bail!("not an object");
+    };
+    let policy = match (str_of(rule, "action"), str_of(rule, "outbound")) {
+        (Some("reject"), _) => "REJECT".to_string(),
+        (None | Some("route"), Some(outbound)) => target(outbound),
+        (Some(action), _) => bail!("unsupported action `{action}`"),
+        (None, None) => bail!("missing outbound"),
+    };
+
+    let mut rules = Vec::new();
+    let mut families = Vec::new();
+    for (key, value) in fields {
+        let values: Vec<String> = match value {
+            Json::Array(values) => values.iter().map(json_to_string).collect(),
+            value => vec![json_to_string(value)],
+        };
+        let (family, kind) = match key.as_str() {
+            "outbound" | "action" => continue,
+            "domain" => ("destination", "DOMAIN"),
+            "domain_suffix" => ("destination", "DOMAIN-SUFFIX"),
+            "domain_keyword" => ("destination", "DOMAIN-KEYWORD"),
+            "domain_regex" => ("destination", "DOMAIN-REGEX"),
+            "geosite" => ("destination", "GEOSITE"),
+            "geoip" => ("destination", "GEOIP"),
+            "ip_cidr" => ("destination", "IP-CIDR"),
+            "port" => ("port", "DST-PORT"),
+            "process_name" => ("process", "PROCESS-NAME"),
+            "process_path" => ("process", "PROCESS-PATH"),
+            key => bail!("unsupported field `{key}`"),
+        };
+        if !families.contains(&family) {
+            families.push(family);
+        }
+        for value in values {
+            let kind = if kind == "IP-CIDR" && value.contains(':') {
+                "IP-CIDR6"
+            } else {
+                kind
+            };
+            let no_resolve = if kind.starts_with("IP-CIDR") {
+                ",no-resolve"
+            } else {
+                ""
+            };
+            rules.push(format!("{kind},{value},{policy}{no_resolve}"));
+        }
+    }
+    if families.len() > 1 {
+        bail!("combines {}", families.join(" and "));
+    }
+    if rules.is_empty() {
+        bail!("matches nothing");
+    }
+    Ok(rules)
+}
+
+/// Render the proxies, groups and rules of a clash config as sing-box
+/// `outbounds` and `route`, the builtin `DIRECT` becomes a direct outbound
+pub(super) fn export(config: &Mapping) -> (Json, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut outbounds = Vec::new();
+    let sequence = |key: &str| {
+        config
+            .get(key)
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_mapping)
+    };
+
+    for proxy in sequence("proxies") {
+        let name = field(proxy, "name").unwrap_or_default();
+        match export_outbound(proxy) {
+            Ok(outbound) => outbounds.push(outbound),
+            Err(err) => warnings.push(format!("proxy `{name}`: {err}, skipped")),
+        }
+    }
+
+    let group_names: Vec<String> = sequence("proxy-groups")
+        .filter_map(|group| field(group, "name"))
+        .collect();
+    let mut known: HashSet<String> = outbounds
+        .iter()
+        .filter_map(|o| str_of(o, "tag"))
+        .map(str::to_string)
+        .chain(group_names)
+        .collect();
+    known.insert("DIRECT".into());
+
+    for group in sequence("proxy-groups") {
+        let name = field(group, "name").unwrap_or_default();
+        let members: Vec<String> = group
+            .get("proxies")
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .filter(|member| {
+                let keep = known.contains(*member);
+                if !keep {
+                    warnings.push(format!("group `{name}`: dropped member `{member}`"));
+                }
+                keep
+            })
+            .map(str::to_string)
+            .collect();
+        if group.contains_key("use") {
+            warnings.push(format!(
+                "group `{name}`: providers in `use` are not exported"
+            ));
+        }
+        let members = match members.is_empty() {
+            true => vec!["DIRECT".to_string()],
+            false => members,
+        };
+        let kind = field(group, "type").unwrap_or_default();
+        let outbound = match kind.as_str() {
+            "select" => json!({
+                "type": "selector",
+                "tag": name,
+                "outbounds": members,
+                "default": members[0],
+            }),
+            "url-test" | "fallback" | "load-balance" => {
+                if kind != "url-test" {
+                    warnings.push(format!("group `{name}`: `{kind}` is exported as urltest"));
+                }
+                let mut outbound = json!({
+                    "type": "urltest",
+                    "tag": name,
+                    "outbounds": members,
+                });
+                if let Some(url) = field(group, "url") {
+                    outbound["url"] = url.into();
+                }
+                if let Some(interval) = field(group, "interval") {
+                    outbound["interval"] = format!("{interval}s").into();
+                }
+                if let Some(tolerance) = group.get("tolerance").and_then(Value::as_u64) {
+                    outbound["tolerance"] = tolerance.into();
+                }
+                outbound
+            }
+            kind => {
+                warnings.push(format!(
+                    "group `{name}`: unsupported type `{kind}`, skipped"
+                ));
+                known.remove(&name);
+                continue;
+            }
+        };
+        outbounds.push(outbound);
+    }
+    outbounds.push(json!({ "type": "direct", "tag": "DIRECT" }));
+
+    let mut rules = Vec::new();
+    let mut last = None;
+    for rule in config
+        .get("rules")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        if let Some(target) = rule.strip_prefix("MATCH,") {
+            last = Some(target.to_string());
+            break;
+        }
+        match export_rule(rule, &known) {
+            Ok(rule) => rules.push(rule),
+            Err(err) => warnings.push(format!("rule `{rule}`: {err}, skipped")),
+        }
+    }
+
+    let mut route = json!({ "rules": rules });
+    if let Some(last) = last.filter(|last| known.contains(last)) {
+        route["final"] = last.into();
+    }
+    (json!({ "outbounds": outbounds, "route": route }), warnings)
+}
+
+fn export_outbound(proxy: &Mapping) -> Result<Json> {
+    let get = |key: &str| field(proxy, key);
+    let kind = get("type").context("missing type")?;
+    let port: u16 = get("port")
+        .and_then(|port| port.parse().ok())
+        .context("missing or invalid port")?;
+    let mut outbound = json!({
+        "tag": get("name").context("missing name")?,
+        "server": get("server").context("missing server")?,
+        "server_port": port,
+    });
+    let mut put = |key: &str, value: Option<String>| {
+        if let Some(value) = value {
+            outbound[key] = value.into();
+        }
+    };
+
+    let (kind, tls) = match kind.as_str() {
+        "ss" => {
+            put("method", Some(get("cipher").context("missing cipher")?));
+            put(
+                "password",
+                Some(get("password").context("missing password")?),
+            );
+            if let Some((plugin, options)) = ss_plugin(proxy)? {
+                put("plugin", Some(plugin.into()));
+                put("plugin_opts", Some(options));
+            }
+            ("shadowsocks", false)
+        }
+        "vmess" => {
+            put("uuid", Some(get("uuid").context("missing uuid")?));
+            put("security", get("cipher"));
+            let alter_id = proxy.get("alterId").and_then(Value::as_u64).unwrap_or(0);
+            outbound["alter_id"] = alter_id.into();
+            ("vmess", false)
+        }
+        "vless" => {
+            put("uuid", Some(get("uuid").context("missing uuid")?));
+            put("flow", get("flow"));
+            ("vless", false)
+        }
+        "trojan" => {
+            put(
+                "password",
+                Some(get("password").context("missing password")?),
+            );
+            ("trojan", true)
+        }
+        "hysteria2" => {
+            put("password", get("password"));
+            if let Some(obfs) = get("obfs") {
+                outbound["obfs"] = json!({ "type": obfs, "password": get("obfs-password") });
+            }
+            for (key, name) in [("up", "up_mbps"), ("down", "down_mbps")] {
+                // `100 Mbps` or a bare number of mbps
+                let mbps = get(key).and_then(|v| v.split_whitespace().next()?.parse::<u64>().ok());
+                if let Some(mbps) = mbps {
+                    outbound[name] = mbps.into();
+                }
+            }
+            ("hysteria2", true)
+        }
+        "tuic" => {
+            put(
+                "uuid",
+                Some(get("uuid").context("tuic v4 tokens are not supported")?),
+            );
+            put("password", get("password"));
+            put("congestion_control", get("congestion-controller"));
+            put("udp_relay_mode", get("udp-relay-mode"));
+            ("tuic", true)
+        }
+        "socks5" => {
+            put("username", get("username"));
+            put("password", get("password"));
+            outbound["version"] = "5".into();
+            ("socks", false)
+        }
+        "http" => {
+            put("username", get("username"));
+            put("password", get("password"));
+            ("http", false)
+        }
+        kind => bail!("unsupported type `{kind}`"),
+    };
+    outbound["type"] = kind.into();
+
+    let tls = tls || proxy.get("tls").and_then(Value::as_bool) == Some(true);
+    if tls {
+        outbound["tls"] = export_tls(proxy);
+    }
+
+    let transport = get_transport(proxy);
+    let transport = match transport.network {
+        "tcp" => None,
+        "ws" if transport.http_upgrade => Some(json!({
+            "type": "httpupgrade",
+            "host": transport.host,
+            "path": transport.path,
+        })),
+        "ws" => {
+            let mut ws = json!({ "type": "ws", "path": transport.path });
+            if let Some(host) = transport.host {
+                ws["headers"] = json!({ "Host": host });
+            }
+            let opts = proxy.get("ws-opts").and_then(Value::as_mapping);
+            if let Some(early_data) = opts.and_then(|o| o.get("max-early-data")?.as_u64()) {
+                ws["max_early_data"] = early_data.into();
+                ws["early_data_header_name"] =
+                    opts.and_then(|o| field(o, "early-data-header-name")).into();
+            }
+            Some(ws)
+        }
+        "grpc" => Some(json!({ "type": "grpc", "service_name": transport.service_name })),
+        // the http transport of sing-box is h2 over tls
+        "h2" => Some(json!({
+            "type": "http",
+            "host": transport.host.into_iter().collect::<Vec<_>>(),
+            "path": transport.path,
+        })),
+        network => bail!("unsupported transport `{network}`"),
+    };
+    if let Some(mut transport) = transport {
+        // drop the `null` of missing options
+        if let Json::Object(map) = &mut transport {
+            map.retain(|_, value| !value.is_null());
+        }
+        outbound["transport"] = transport;
+    }
+
+    Ok(outbound)
+}
+
+/// the reverse of `set_tls`
+fn export_tls(proxy: &Mapping) -> Json {
+    let mut tls = Map::new();
+    tls.insert("enabled".into(), true.into());
+    if let Some(name) = field(proxy, "servername").or_else(|| field(proxy, "sni")) {
+        tls.insert("server_name".into(), name.into());
+    }
+    if proxy.get("skip-cert-verify").and_then(Value::as_bool) == Some(true) {
+        tls.insert("insecure".into(), true.into());
+    }
+    let alpn: Vec<&str> = proxy
+        .get("alpn")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    if !alpn.is_empty() {
+        tls.insert("alpn".into(), alpn.into());
+    }
+    if let Some(fingerprint) = field(proxy, "client-fingerprint") {
+        tls.insert(
+            "utls".into(),
+            json!({ "enabled": true, "fingerprint": fingerprint }),
+        );
+    }
+    if let Some(reality) = proxy.get("reality-opts").and_then(Value::as_mapping) {
+        tls.insert(
+            "reality".into(),
+            json!({
+                "enabled": true,
+                "public_key": field(reality, "public-key"),
+                "short_id": field(reality, "short-id"),
+            }),
+        );
+    }
+    Json::Object(tls)
+}
+
+/// The reverse of `convert_rule`, one sing-box rule per clash rule
+fn export_rule(rule: &str, known: &HashSet<String>) -> Result<Json> {
+    let fields: Vec<&str> = rule.split(',').map(str::trim).collect();
+    let [kind, payload, target, ..] = fields.as_slice() else {
+        bail!("unsupported rule");
+    };
+    let mut json = match *kind {
+        "GEOIP" if payload.eq_ignore_ascii_case("lan") => json!({ "ip_is_private": true }),
+        "DST-PORT" => {
+            let port: u16 = payload.parse().context("port ranges are not supported")?;
+            json!({ "port": [port] })
+        }
+        kind => {
+            let key = match kind {
+                "DOMAIN" => "domain",
+                "DOMAIN-SUFFIX" => "domain_suffix",
+                "DOMAIN-KEYWORD" => "domain_keyword",
+                "DOMAIN-REGEX" => "domain_regex",
+                "GEOSITE" => "geosite",
+                "GEOIP" => "geoip",
+                "IP-CIDR" | "IP-CIDR6" => "ip_cidr",
+                "SRC-IP-CIDR" => "source_ip_cidr",
+                "PROCESS-NAME" => "process_name",
+                "PROCESS-PATH" => "process_path",
+                kind => bail!("unsupported type `{kind}`"),
+            };
+            json!({ key: [payload] })
+        }
+    };
+    match *target {
+        "REJECT" | "REJECT-DROP" => json["action"] = "reject".into(),
+        target if known.contains(target) => json["outbound"] = target.into(),
+        target => bail!("unknown target `{target}`"),
+    }
+    Ok(json)
+}
+
+/// `30s`, `3m`, `1h` in seconds
+fn parse_duration(value: &str) -> Option<u64> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    match unit {
+        "s" => Some(number),
+        "m" => Some(number * 60),
+        "h" => Some(number * 3600),
+        _ => None,
+    }
+}
+
+fn str_of<'a>(value: &'a Json, key: &str) -> Option<&'a str> {
+    value.get(key)?.as_str().filter(|s| !s.is_empty())
+}
+
+fn required<'a>(value: &'a Json, key: &str) -> Result<&'a str> {
+    str_of(value, key).with_context(|| format!("missing {key}"))
+}
+
+fn json_to_string(value: &Json) -> String {
+    match value {
+        Json::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "outbounds": [
+            {"type": "selector", "tag": "Proxy", "outbounds": ["auto", "hk", "jp", "direct"], "default": "hk"},
+            {"type": "urltest", "tag": "auto", "outbounds": ["hk", "jp"], "interval": "3m"},
+            {"type": "vless", "tag": "hk", "server": "hk.example.com", "server_port": 443,
+                "uuid": "b831381d-6324-4d53-ad4f-8cda48b30811", "flow": "xtls-rprx-vision",
+                "tls": {"enabled": true, "server_name": "www.microsoft.com",
+                    "utls": {"enabled": true, "fingerprint": "chrome"},
+                    "reality": {"enabled": true, "public_key": "pbk", "short_id": "sid"}}},
+            {"type": "trojan", "tag": "jp", "server": "jp.example.com", "server_port": 443,
+                "password": "pass", "tls": {"enabled": true, "server_name": "jp.example.com"},
+                "transport": {"type": "ws", "path": "/ws", "headers": {"Host": "cdn.example.com"}}},
+            {"type": "wireguard", "tag": "wg", "server": "wg.example.com", "server_port": 51820},
+            {"type": "direct", "tag": "direct"},
+            {"type": "block", "tag": "block"},
+            {"type": "dns", "tag": "dns-out"}
+        ],
+        "route": {
+            "rules": [
+                {"domain_suffix": ["cn", "example.cn"], "ip_cidr": ["10.0.0.0/8"], "outbound": "direct"},
+                {"domain": "ads.example.com", "outbound": "block"},
+                {"protocol": "dns", "outbound": "dns-out"},
+                {"domain": "x.com", "port": 443, "outbound": "Proxy"},
+                {"rule_set": "geosite-cn", "outbound": "direct"}
+            ],
+            "final": "Proxy"
+        }
+    }"#;
+
+    #[test]
+    fn test_convert_singbox() {
+        let json: Json = serde_json::from_str(CONFIG).unwrap();
+        let converted = convert(&json).unwrap();
+        let config = converted.config;
+
+        let proxies = config["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 2);
+        let hk = &proxies[0];
+        assert_eq!(hk["type"], "vless");
+        assert_eq!(hk["servername"], "www.microsoft.com");
+        assert_eq!(hk["client-fingerprint"], "chrome");
+        assert_eq!(hk["reality-opts"]["public-key"], "pbk");
+        let jp = &proxies[1];
+        assert_eq!(jp["sni"], "jp.example.com");
+        assert_eq!(jp["ws-opts"]["headers"]["Host"], "cdn.example.com");
+
+        let groups = config["proxy-groups"].as_sequence().unwrap();
+        assert_eq!(groups[0]["name"], "Proxy");
+        assert_eq!(
+            groups[0]["proxies"],
+            serde_yaml::from_str::<serde_yaml::Value>("[hk, auto, jp, DIRECT]").unwrap()
+        );
+        assert_eq!(groups[1]["type"], "url-test");
+        assert_eq!(groups[1]["interval"], 180);
+
+        let rules: Vec<&str> = config["rules"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|r| r.as_str().unwrap())
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                "DOMAIN-SUFFIX,cn,DIRECT",
+                "DOMAIN-SUFFIX,example.cn,DIRECT",
+                "IP-CIDR,10.0.0.0/8,DIRECT,no-resolve",
+                "DOMAIN,ads.example.com,REJECT",
+                "MATCH,Proxy",
+            ]
+        );
+
+        // wireguard, the dns rule, the combined rule and the rule set
+        assert_eq!(converted.warnings.len(), 4, "{:?}", converted.warnings);
+    }
+
+    #[test]
+    fn test_export_singbox() {
+        let json: Json = serde_json::from_str(CONFIG).unwrap();
+        let config = convert(&json).unwrap().config;
+        let (exported, warnings) = export(&config);
+        assert!(warnings.is_empty(), "{warnings:?}");
+
+        let outbounds = exported["outbounds"].as_array().unwrap();
+        let tags: Vec<&str> = outbounds
+            .iter()
+            .map(|o| o["tag"].as_str().unwrap())
+            .collect();
+        assert_eq!(tags, ["hk", "jp", "Proxy", "auto", "DIRECT"]);
+        assert_eq!(outbounds[0]["tls"]["reality"]["public_key"], "pbk");
+        assert_eq!(
+            outbounds[1]["transport"]["headers"]["Host"],
+            "cdn.example.com"
+        );
+        assert_eq!(exported["route"]["rules"][3]["action"], "reject");
+        assert_eq!(exported["route"]["final"], "Proxy");
+
+        // and back again
+        assert_eq!(convert(&exported).unwrap().config, config);
+    }
+}
@@ -0,0 +1,378 @@
+//! Surge configs: `[Proxy]`, `[Proxy Group]` and `[Rule]`
+
+use super::{
+    is_true, key_values, lookup, section, set, set_opt, set_transport, Converted, Profile,
+};
+use anyhow::{bail, Context, Result};
+use serde_yaml::Mapping;
+use std::collections::HashMap;
+
+pub(super) fn convert(sections: &[(String, Vec<String>)]) -> Result<Converted> {
+    let mut profile = Profile::default();
+    // `Name = direct` and `Name = reject` are aliases of the builtins
+    let mut builtins = HashMap::new();
+
+    for line in section(sections, "proxy") {
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        let name = name.trim();
+        match convert_proxy(name, value) {
+            Ok(Proxy::Builtin(builtin)) => {
+                builtins.insert(name.to_string(), builtin);
+            }
+            Ok(Proxy::Proxy(proxy)) => profile.proxies.push(proxy),
+            Err(err) => profile
+                .warnings
+                .push(format!("proxy `{name}`: {err}, skipped")),
+        }
+    }
+
+    let policy = |name: &str| policy_name(name, &builtins);
+    for line in section(sections, "proxy group") {
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        let name = name.trim();
+        let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+        let kind = match fields[0] {
+            "select" => "select",
+            "url-test" | "smart" => "url-test",
+            "fallback" => "fallback",
+            "load-balance" => "load-balance",
+            kind => {
+                profile.warnings.push(format!(
+                    "group `{name}`: unsupported type `{kind}`, skipped"
+                ));
+                continue;
+            }
+        };
+        let members: Vec<String> = fields[1..]
+            .iter()
+            .filter(|field| !field.contains('='))
+            .map(|member| policy(member))
+            .collect();
+        let options = key_values(fields[1..].iter().copied());
+        if lookup(&options, "policy-path").is_some() {
+            profile.warnings.push(format!(
+                "group `{name}`: `policy-path` is not downloaded, use a proxy provider instead"
+            ));
+        }
+
+        let mut group = Mapping::new();
+        set(&mut group, "name", name);
+        set(&mut group, "type", kind);
+        set(&mut group, "proxies", members);
+        if kind != "select" {
+            let url = lookup(&options, "url").unwrap_or("http://www.gstatic.com/generate_204");
+            set(&mut group, "url", url);
+            let interval = lookup(&options, "interval").and_then(|i| i.parse::<u64>().ok());
+            set(&mut group, "interval", interval.unwrap_or(600));
+            set_opt(
+                &mut group,
+                "tolerance",
+                lookup(&options, "tolerance").and_then(|t| t.parse::<u64>().ok()),
+            );
+        }
+        if lookup(&options, "include-all-proxies").is_some_and(is_true) {
+            set(&mut group, "include-all-proxies", true);
+        }
+        profile.groups.push(group);
+    }
+
+    for line in section(sections, "rule") {
+        match convert_rule(line, policy) {
+            Ok(rule) => profile.rules.push(rule),
+            Err(err) => profile
+                .warnings
+                .push(format!("rule `{line}`: {err}, skipped")),
+        }
+    }
+
+    profile.build("surge")
+}
+
+enum Proxy {
+    Proxy(Mapping),
+    Builtin(&'static str),
+}
+
+/// `type, server, port, key=value, ...`
+fn convert_proxy(name: &str, value: &str) -> Result<Proxy> {
+    let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+    let kind = fields[0].to_lowercase();
+    match kind.as_str() {
+        "direct" => return Ok(Proxy::Builtin("DIRECT")),
+        "reject" | "reject-tinygif" | "reject-drop" => return Ok(Proxy::Builtin("REJECT")),
+        _ => {}
+    }
+
+    let server = fields.get(1).context("missing server")?;
+    let port: u16 = fields
+        .get(2)
+        .and_then(|port| port.parse().ok())
+        .context("missing or invalid port")?;
+    let options = key_values(fields[3..].iter().copied());
+    let option = |key: &str| lookup(&options, key);
+    // `http, server, port, user, pass` of older configs
+    let positional: Vec<&str> = fields[3..]
+        .iter()
+        .copied()
+        .filter(|f| !f.contains('='))
+        .collect();
+
+    let mut proxy = Mapping::new();
+    set(&mut proxy, "name", name);
+    match kind.as_str() {
+        "ss" => {
+            set(&mut proxy, "type", "ss");
+            set(&mut proxy, "server", *server);
+            set(&mut proxy, "port", port);
+            set(
+                &mut proxy,
+                "cipher",
+                option("encrypt-method").context("missing encrypt-method")?,
+            );
+            set(
+                &mut proxy,
+                "password",
+                option("password").context("missing password")?,
+            );
+            set(&mut proxy, "udp", option("udp-relay").is_some_and(is_true));
+            if let Some(obfs) = option("obfs") {
+                let mut opts = Mapping::new();
+                set(&mut opts, "mode", obfs);
+                set_opt(&mut opts, "host", option("obfs-host"));
+                set(&mut proxy, "plugin", "obfs");
+                set(&mut proxy, "plugin-opts", opts);
+            }
+        }
+        "vmess" => {
+            set(&mut proxy, "type", "vmess");
+            set(&mut proxy, "server", *server);
+            set(&mut proxy, "port", port);
+            set(
+                &mut proxy,
+                "uuid",
+                option("username").context("missing username")?,
+            );
+            let aead = option("vmess-aead").is_none_or(is_true);
+            set(&mut proxy, "alterId", if aead { 0 } else { 1 });
+            set(
+                &mut proxy,
+                "cipher",
+                option("encrypt-method").unwrap_or("auto"),
+            );
+            set(&mut proxy, "udp", true);
+            if option("tls").is_some_and(is_true) {
+                set(&mut proxy, "tls", true);
+                set_opt(&mut proxy, "servername", option("sni"));
+            }
+        }
+        "trojan" => {
+            set(&mut proxy, "type", "trojan");
+            set(&mut proxy, "server", *server);
+            set(&mut proxy, "port", port);
+            set(
+                &mut proxy,
+                "password",
+                option("password").context("missing password")?,
+            );
+            set_opt(&mut proxy, "sni", option("sni"));
+            set(&mut proxy, "udp", true);
+        }
+        "http" | "https" | "socks5" | "socks5-tls" => {
+            let (kind, tls) = match kind.as_str() {
+                "http" => ("http", false),
+                "https" => ("http", true),
+                "socks5" => ("socks5", false),
+                _ => ("socks5", true),
+            };
+            set(&mut proxy, "type", kind);
+            set(&mut proxy, "server", *server);
+            set(&mut proxy, "port", port);
+            let username = option("username").or(positional.first().copied());
+            let password = option("password").or(positional.get(1).copied());
+            set_opt(&mut proxy, "username", username);
+            set_opt(&mut proxy, "password", password);
+            if tls {
+                set(&mut proxy, "tls", true);
+                set_opt(&mut proxy, "sni", option("sni"));
+            }
+        }
+        "snell" => {
+            set(&mut proxy, "type", "snell");
+            set(&mut proxy, "server", *server);
+            set(&mut proxy, "port", port);
+            set(&mut proxy, "psk", option("psk").context("missing psk")?);
+            let version = option("version").and_then(|v| v.parse::<u8>().ok());
+            set(&mut proxy, "version", version.unwrap_or(1));
+            if let Some(obfs) = option("obfs") {
+                let mut opts = Mapping::new();
+                set(&mut opts, "mode", obfs);
+                set_opt(&mut opts, "host", option("obfs-host"));
+                set(&mut proxy, "obfs-opts", opts);
+            }
+        }
+        "hysteria2" => {
+            set(&mut proxy, "type", "hysteria2");
+            set(&mut proxy, "server", *server);
+            set(&mut proxy, "port", port);
+            set(
+                &mut proxy,
+                "password",
+                option("password").context("missing password")?,
+            );
+            set_opt(&mut proxy, "sni", option("sni"));
+            set_opt(&mut proxy, "down", option("download-bandwidth"));
+        }
+        "tuic" | "tuic-v5" => {
+            set(&mut proxy, "type", "tuic");
+            set(&mut proxy, "server", *server);
+            set(&mut proxy, "port", port);
+            match option("uuid") {
+                Some(uuid) => {
+                    set(&mut proxy, "uuid", uuid);
+                    set_opt(&mut proxy, "password", option("password"));
+                }
+                None => set(
+                    &mut proxy,
+                    "token",
+                    option("token").context("missing token")?,
+                ),
+            }
+            set_opt(&mut proxy, "sni", option("sni"));
+            set_opt(&mut proxy, "alpn", option("alpn").map(|alpn| vec![alpn]));
+            set(&mut proxy, "udp", true);
+        }
+        kind => bail!("unsupported type `{kind}`"),
+    }
+
+    if option("skip-cert-verify").is_some_and(is_true) {
+        set(&mut proxy, "skip-cert-verify", true);
+    }
+    if option("tfo").is_some_and(is_true) {
+        set(&mut proxy, "tfo", true);
+    }
+    if option("ws").is_some_and(is_true) {
+        // `ws-headers=Host:example.com|User-Agent:...`
+        let host = option("ws-headers").and_then(|headers| {
+            headers
+                .split('|')
+                .filter_map(|header| header.split_once(':'))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("host"))
+                .map(|(_, value)| value.trim().trim_matches('"'))
+        });
+        set_transport(&mut proxy, "ws", host, option("ws-path"), None)?;
+    }
+
+    Ok(Proxy::Proxy(proxy))
+}
+
+/// Surge and clash share most rule types, `FINAL` is `MATCH`
+fn convert_rule(line: &str, policy: impl Fn(&str) -> String) -> Result<String> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    let kind = fields[0].to_uppercase();
+    match kind.as_str() {
+        "FINAL" => {
+            let target = fields.get(1).context("missing policy")?;
+            Ok(format!("MATCH,{}", policy(target)))
+        }
+        "DOMAIN" | "DOMAIN-SUFFIX" | "DOMAIN-KEYWORD" | "IP-CIDR" | "IP-CIDR6" | "GEOIP"
+        | "IP-ASN" | "PROCESS-NAME" | "DEST-PORT" | "DST-PORT" | "SRC-IP" | "SRC-PORT" => {
+            let payload = fields.get(1).context("missing payload")?;
+            let target = fields.get(2).context("missing policy")?;
+            let kind = match kind.as_str() {
+                "DEST-PORT" => "DST-PORT",
+                "SRC-IP" => "SRC-IP-CIDR",
+                kind => kind,
+            };
+            let mut rule = format!("{kind},{payload},{}", policy(target));
+            if fields[3..].contains(&"no-resolve") {
+                rule.push_str(",no-resolve");
+            }
+            Ok(rule)
+        }
+        "RULE-SET" | "DOMAIN-SET" => bail!("remote rule sets are not downloaded"),
+        kind => bail!("unsupported type `{kind}`"),
+    }
+}
+
+fn policy_name(name: &str, builtins: &HashMap<String, &'static str>) -> String {
+    match name.to_uppercase().as_str() {
+        "DIRECT" => "DIRECT".into(),
+        "REJECT" | "REJECT-TINYGIF" | "REJECT-DROP" | "REJECT-NO-DROP" => "REJECT".into(),
+        _ => builtins
+            .get(name)
+            .map_or_else(|| name.to_string(), |b| b.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ini_sections;
+    use super::*;
+
+    const CONFIG: &str = r#"
+[General]
+loglevel = notify
+
+[Proxy]
+Direct = direct
+HK = ss, hk.example.com, 8388, encrypt-method=aes-128-gcm, password=pass, obfs=http, obfs-host=bing.com, udp-relay=true
+JP = vmess, jp.example.com, 443, username=b831381d-6324-4d53-ad4f-8cda48b30811, ws=true, ws-path=/v2, ws-headers=Host:cdn.example.com, tls=true, sni=jp.example.com
+US = trojan, us.example.com, 443, password=pass, skip-cert-verify=true
+Office = http, 10.0.0.1, 8080, user, secret
+WG = wireguard, section-name=wg
+
+[Proxy Group]
+Proxy = select, Auto, HK, JP, US, Office, Direct
+Auto = url-test, HK, JP, url=http://cp.cloudflare.com/generate_204, interval=300
+Remote = select, policy-path=https://example.com/nodes.txt
+
+[Rule]
+DOMAIN-SUFFIX,example.cn,Direct
+IP-CIDR,10.0.0.0/8,DIRECT,no-resolve
+RULE-SET,https://example.com/ads.list,REJECT
+USER-AGENT,Telegram*,Proxy
+FINAL,Proxy,dns-failed
+"#;
+
+    #[test]
+    fn test_convert_surge() {
+        let converted = convert(&ini_sections(CONFIG)).unwrap();
+        let config = converted.config;
+
+        let proxies = config["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 4);
+        assert_eq!(proxies[0]["plugin-opts"]["host"], "bing.com");
+        assert_eq!(proxies[1]["ws-opts"]["path"], "/v2");
+        assert_eq!(proxies[1]["ws-opts"]["headers"]["Host"], "cdn.example.com");
+        assert_eq!(proxies[1]["servername"], "jp.example.com");
+        assert_eq!(proxies[3]["username"], "user");
+
+        let groups = config["proxy-groups"].as_sequence().unwrap();
+        assert_eq!(groups[0]["proxies"][5], "DIRECT");
+        assert_eq!(groups[1]["interval"], 300);
+        // the remote policy group falls back to DIRECT
+        assert_eq!(groups[2]["proxies"][0], "DIRECT");
+
+        let rules: Vec<&str> = config["rules"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|r| r.as_str().unwrap())
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                "DOMAIN-SUFFIX,example.cn,DIRECT",
+                "IP-CIDR,10.0.0.0/8,DIRECT,no-resolve",
+                "MATCH,Proxy",
+            ]
+        );
+
+        // wireguard, policy-path, the rule set and the user agent rule
+        assert_eq!(converted.warnings.len(), 4, "{:?}", converted.warnings);
+    }
+}
@@ -3,11 +3,13 @@ use crate::core::tray::Tray;
 use crate::{
     config::*,
     core::{handle, service},
-    log_err,
+    enhance::lint::Level,
+    feat, log_err,
     module::mihomo::MihomoManager,
     utils::{dirs, help},
 };
 use anyhow::{bail, Result};
+use mihomo_api::MihomoError;
 use once_cell::sync::OnceCell;
 use std::{path::PathBuf, sync::Arc, time::Duration};
 use tauri_plugin_shell::ShellExt;
@@ -134,6 +136,11 @@ impl CoreManager {
 
         *running = true;
 
+        // 内核重启后恢复各分组上次手动选择的节点
+        tauri::async_runtime::spawn(async {
+            log_err!(feat::restore_selected().await);
+        });
+
         Ok(())
     }
 
@@ -186,6 +193,7 @@ impl CoreManager {
             config: Some(Config::clash().latest().0.clone()),
             exists_keys: vec![],
             chain_logs: Default::default(),
+            diagnostics: vec![],
         };
         help::save_yaml(
             &runtime_path,
@@ -536,31 +544,36 @@ impl CoreManager {
                 let run_path = dirs::path_to_str(&run_path)?;
 
                 // 5. 应用新配置
+                // 内核还没起来时退避重试，配置被内核拒绝或密钥不对时重试无意义
                 println!("[core配置更新] 应用新配置");
-                for i in 0..3 {
+                let mut retry = 0;
+                loop {
                     match MihomoManager::global().put_configs_force(run_path).await {
                         Ok(_) => {
                             println!("[core配置更新] 配置应用成功");
                             Config::runtime().apply();
+                            log_err!(feat::restore_selected().await);
                             return Ok((true, String::new()));
                         }
+                        Err(MihomoError::ConnectionRefused(err)) if retry < 5 => {
+                            retry += 1;
+                            println!("[core配置更新] 内核未就绪，第{}次重试应用配置", retry);
+                            log::info!(target: "app", "core not ready: {err}");
+                            sleep(Duration::from_millis(100 << retry)).await;
+                        }
                         Err(err) => {
-                            if i < 2 {
-                                println!("[core配置更新] 第{}次重试应用配置", i + 1);
-                                log::info!(target: "app", "{err}");
-                                sleep(Duration::from_millis(100)).await;
-                            } else {
-                                println!("[core配置更新] 配置应用失败: {}", err);
-                                Config::runtime().discard();
-                                return Ok((false, err.to_string()));
-                            }
+                            println!("[core配置更新] 配置应用失败: {}", err);
+                            log::error!(target: "app", "failed to apply config: {err}");
+                            let error_msg = with_lint_errors(err.to_string());
+                            Config::runtime().discard();
+                            return Ok((false, error_msg));
                         }
                     }
                 }
-                Ok((true, String::new()))
             }
             Ok((false, error_msg)) => {
                 println!("[core配置更新] 配置验证失败: {}", error_msg);
+                let error_msg = with_lint_errors(error_msg);
                 Config::runtime().discard();
                 Ok((false, error_msg))
             }
@@ -636,3 +649,19 @@ impl CoreManager {
         }
     }
 }
+
+/// 内核拒绝配置时附上静态检查发现的错误，草稿丢弃后就看不到了
+fn with_lint_errors(error_msg: String) -> String {
+    let runtime = Config::runtime();
+    let runtime = runtime.latest();
+    let errors: Vec<String> = runtime
+        .diagnostics
+        .iter()
+        .filter(|d| d.level == Level::Error)
+        .map(|d| format!("{}: {}", d.path, d.message))
+        .collect();
+    if errors.is_empty() {
+        return error_msg;
+    }
+    format!("{error_msg}\n{}", errors.join("\n"))
+}
@@ -72,6 +72,13 @@ impl Handle {
         }
     }
 
+    /// 批量测速时每个节点的结果，`delay` 为 `None` 表示失败
+    pub fn proxy_delay(group: &str, name: &str, delay: Option<u32>) {
+        if let Some(window) = Self::global().get_window() {
+            log_err!(window.emit("verge://proxy-delay", (group, name, delay)));
+        }
+    }
+
     pub fn notice_message<S: Into<String>, M: Into<String>>(status: S, msg: M) {
         if let Some(window) = Self::global().get_window() {
             log_err!(window.emit("verge://notice-message", (status.into(), msg.into())));
@@ -1,4 +1,8 @@
-use crate::{config::Config, core::CoreManager, feat};
+use crate::{
+    config::Config,
+    core::{handle, CoreManager},
+    feat,
+};
 use anyhow::{Context, Result};
 use delay_timer::prelude::{DelayTimer, DelayTimerBuilder, TaskBuilder};
 use once_cell::sync::OnceCell;
@@ -302,6 +306,13 @@ impl Timer {
             }
             Err(e) => {
                 log::error!(target: "app", "Failed to update profile uid {}: {}", uid, e);
+                let name = Config::profiles()
+                    .latest()
+                    .get_item(&uid)
+                    .ok()
+                    .and_then(|item| item.name.clone())
+                    .unwrap_or(uid);
+                handle::Handle::notice_message("update_profile::error", format!("{name}: {e}"));
             }
         }
     }
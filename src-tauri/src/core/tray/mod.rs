@@ -11,15 +11,19 @@ use crate::{
     utils::{dirs, i18n::t, resolve::VERSION},
 };
 
+#[cfg(target_os = "macos")]
+use crate::module::mihomo::MihomoManager;
 use anyhow::Result;
 #[cfg(target_os = "macos")]
 use futures::StreamExt;
 #[cfg(target_os = "macos")]
+use mihomo_api::{Backoff, Traffic};
+#[cfg(target_os = "macos")]
 use parking_lot::Mutex;
 #[cfg(target_os = "macos")]
 use parking_lot::RwLock;
 #[cfg(target_os = "macos")]
-pub use speed_rate::{SpeedRate, Traffic};
+pub use speed_rate::SpeedRate;
 #[cfg(target_os = "macos")]
 use std::sync::Arc;
 use tauri::{
@@ -329,42 +333,30 @@ impl Tray {
         *self.is_subscribed.write() = true;
 
         let speed_rate = Arc::clone(&self.speed_rate);
-        let is_subscribed = Arc::clone(&self.is_subscribed);
-
         tauri::async_runtime::spawn(async move {
             let mut shutdown = shutdown_rx;
 
-            'outer: loop {
-                match Traffic::get_traffic_stream().await {
-                    Ok(mut stream) => loop {
-                        tokio::select! {
-                            Some(traffic) = stream.next() => {
-                                if let Ok(traffic) = traffic {
-                                    let guard = speed_rate.lock();
-                                    let enable_tray_speed: bool = Config::verge().latest().enable_tray_speed.unwrap_or(true);
-                                    if !enable_tray_speed {
-                                        continue;
-                                    }
-                                    if let Some(sr) = guard.as_ref() {
-                                        if let Some(rate) = sr.update_and_check_changed(traffic.up, traffic.down) {
-                                            let _ = Tray::global().update_icon(Some(rate));
-                                        }
-                                    }
-                                }
-                            }
-                            _ = shutdown.recv() => break 'outer,
+            // 断线重连由 mihomo_api 处理，每次重连都取当前的端口、密钥和传输方式
+            let mut stream = Box::pin(mihomo_api::MihomoManager::ws_stream_from::<Traffic, _>(
+                MihomoManager::global,
+                "/traffic",
+                Backoff::default(),
+            ));
+            loop {
+                tokio::select! {
+                    Some(traffic) = stream.next() => {
+                        let guard = speed_rate.lock();
+                        let enable_tray_speed: bool = Config::verge().latest().enable_tray_speed.unwrap_or(true);
+                        if !enable_tray_speed {
+                            continue;
                         }
-                    },
-                    Err(e) => {
-                        log::error!(target: "app", "Failed to get traffic stream: {}", e);
-                        // 如果获取流失败，等待一段时间后重试
-                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-
-                        // 检查是否应该继续重试
-                        if !*is_subscribed.read() {
-                            break;
+                        if let Some(sr) = guard.as_ref() {
+                            if let Some(rate) = sr.update_and_check_changed(traffic.up, traffic.down) {
+                                let _ = Tray::global().update_icon(Some(rate));
+                            }
                         }
                     }
+                    _ = shutdown.recv() => break,
                 }
             }
         });
@@ -0,0 +1,197 @@
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+
+/// the select group offering the source groups and the url-test group
+pub const AGGREGATE_SELECT: &str = "Aggregate";
+/// the url-test group over the proxies of every source
+pub const AGGREGATE_AUTO: &str = "Aggregate Auto";
+
+const TEST_URL: &str = "http://www.gstatic.com/generate_204";
+
+/// Names of proxies and groups that are already used
+struct Names(HashSet<String>);
+
+impl Names {
+    /// `name` itself when it is free, else `name (source)`, else numbered
+    fn claim(&mut self, name: &str, source: &str) -> String {
+        let mut candidates = vec![name.to_string()];
+        if !source.is_empty() {
+            candidates.push(format!("{name} ({source})"));
+        }
+        let base = candidates.last().cloned().unwrap_or_default();
+        let name = candidates
+            .into_iter()
+            .chain((2..).map(|i| format!("{base} {i}")))
+            .find(|name| !self.0.contains(name))
+            .unwrap_or_default();
+        self.0.insert(name.clone());
+        name
+    }
+}
+
+/// Merge the proxies and proxy providers of several profiles into `base`
+/// `sources` are the names and configs of the profiles, their groups and rules are dropped
+/// Every source gets a select group, all proxies a url-test group,
+/// and a select group over them is the target of the default rule
+pub fn use_aggregate(mut base: Mapping, sources: Vec<(String, Mapping)>) -> Mapping {
+    let seq = |config: &Mapping, key: &str| -> Vec<Value> {
+        config
+            .get(key)
+            .and_then(Value::as_sequence)
+            .cloned()
+            .unwrap_or_default()
+    };
+    let name_of = |item: &Value| {
+        item.get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let mut proxies = seq(&base, "proxies");
+    let mut groups = seq(&base, "proxy-groups");
+    let mut providers = base
+        .get("proxy-providers")
+        .and_then(Value::as_mapping)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut names = Names(
+        proxies
+            .iter()
+            .chain(groups.iter())
+            .map(name_of)
+            .chain(["DIRECT", "REJECT"].map(String::from))
+            .collect(),
+    );
+    let mut provider_names = Names(
+        providers
+            .keys()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect(),
+    );
+
+    let mut source_groups = Vec::new();
+    let mut all_proxies = Vec::new();
+    let mut all_providers = Vec::new();
+    for (source, config) in sources {
+        let mut group_proxies = Vec::new();
+        for mut proxy in seq(&config, "proxies") {
+            let name = names.claim(&name_of(&proxy), &source);
+            let Some(map) = proxy.as_mapping_mut() else {
+                continue;
+            };
+            map.insert("name".into(), name.clone().into());
+            group_proxies.push(Value::from(name));
+            proxies.push(proxy);
+        }
+
+        let mut group_providers = Vec::new();
+        let source_providers = config.get("proxy-providers").and_then(Value::as_mapping);
+        for (key, provider) in source_providers.into_iter().flatten() {
+            let name = provider_names.claim(key.as_str().unwrap_or_default(), &source);
+            group_providers.push(Value::from(name.clone()));
+            providers.insert(name.into(), provider.clone());
+        }
+
+        if group_proxies.is_empty() && group_providers.is_empty() {
+            continue;
+        }
+        let name = names.claim(&source, "");
+        groups.push(group(&name, "select", &group_proxies, &group_providers).into());
+        source_groups.push(Value::from(name));
+        all_proxies.append(&mut group_proxies);
+        all_providers.append(&mut group_providers);
+    }
+
+    let select = names.claim(AGGREGATE_SELECT, "");
+    let mut select_proxies = Vec::new();
+    if !all_proxies.is_empty() || !all_providers.is_empty() {
+        let auto = names.claim(AGGREGATE_AUTO, "");
+        let mut auto_group = group(&auto, "url-test", &all_proxies, &all_providers);
+        auto_group.insert("url".into(), TEST_URL.into());
+        auto_group.insert("interval".into(), 300.into());
+        groups.insert(0, auto_group.into());
+        select_proxies.push(Value::from(auto));
+    }
+    select_proxies.append(&mut source_groups);
+    select_proxies.push("DIRECT".into());
+    groups.insert(0, group(&select, "select", &select_proxies, &[]).into());
+
+    base.insert("proxies".into(), proxies.into());
+    base.insert("proxy-groups".into(), groups.into());
+    if !providers.is_empty() {
+        base.insert("proxy-providers".into(), providers.into());
+    }
+    if seq(&base, "rules").is_empty() {
+        base.insert("rules".into(), vec![format!("MATCH,{select}")].into());
+    }
+    base
+}
+
+fn group(name: &str, gtype: &str, proxies: &[Value], providers: &[Value]) -> Mapping {
+    let mut group = Mapping::new();
+    group.insert("name".into(), name.into());
+    group.insert("type".into(), gtype.into());
+    if !proxies.is_empty() {
+        group.insert("proxies".into(), proxies.to_vec().into());
+    }
+    if !providers.is_empty() {
+        group.insert("use".into(), providers.to_vec().into());
+    }
+    group
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate() {
+        let a = r#"
+proxies:
+  - {name: hk, type: ss, server: a.com, port: 1}
+  - {name: jp, type: ss, server: a.com, port: 2}
+proxy-groups:
+  - {name: A, type: select, proxies: [hk, jp]}
+rules: ["MATCH,A"]
+"#;
+        let b = r#"
+proxies:
+  - {name: hk, type: trojan, server: b.com, port: 1}
+proxy-providers:
+  extra: {type: http, url: "https://b.com/extra", path: ./extra.yaml}
+"#;
+        let sources = vec![
+            ("A".to_string(), serde_yaml::from_str(a).unwrap()),
+            ("B".to_string(), serde_yaml::from_str(b).unwrap()),
+        ];
+        let config = use_aggregate(Mapping::new(), sources);
+
+        let names = |key: &str| -> Vec<String> {
+            config[key]
+                .as_sequence()
+                .unwrap()
+                .iter()
+                .map(|item| item["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(names("proxies"), ["hk", "jp", "hk (B)"]);
+        assert_eq!(
+            names("proxy-groups"),
+            [AGGREGATE_SELECT, AGGREGATE_AUTO, "A", "B"]
+        );
+
+        let groups = config["proxy-groups"].as_sequence().unwrap();
+        assert_eq!(
+            groups[0]["proxies"],
+            serde_yaml::from_str::<Value>(&format!("[{AGGREGATE_AUTO}, A, B, DIRECT]")).unwrap()
+        );
+        assert_eq!(groups[1]["type"], "url-test");
+        assert_eq!(groups[1]["use"][0], "extra");
+        assert_eq!(groups[3]["proxies"][0], "hk (B)");
+        assert!(config["proxy-providers"].get("extra").is_some());
+        assert_eq!(config["rules"][0], format!("MATCH,{AGGREGATE_SELECT}"));
+    }
+}
@@ -0,0 +1,75 @@
+// Helpers exposed to enhancement scripts as the frozen `utils` object
+// Bump the version when a helper is added or changes its behavior
+function __verge_utils__(context) {
+  const nameOf = (proxy) =>
+    typeof proxy === "string" ? proxy : proxy && proxy.name;
+
+  // a fresh regex every time, `g` and `y` would make `test` stateful
+  const toRegExp = (pattern) =>
+    pattern instanceof RegExp
+      ? new RegExp(pattern.source, pattern.flags.replace(/[gy]/g, ""))
+      : new RegExp(pattern);
+
+  // a string marker matches the whole rule or its leading fields, like `MATCH`
+  const ruleMatcher = (marker) => {
+    if (marker instanceof RegExp) {
+      const re = toRegExp(marker);
+      return (rule) => re.test(rule);
+    }
+    return (rule) => rule === marker || rule.startsWith(`${marker},`);
+  };
+
+  const insertRules = (config, marker, rules, offset) => {
+    const list = Array.isArray(config.rules) ? config.rules : [];
+    let index = list.findIndex(ruleMatcher(marker));
+    if (index === -1) {
+      // keep the new rules in front of the final MATCH
+      const last = list.length - 1;
+      index = last >= 0 && /^MATCH,/i.test(list[last]) ? last : list.length;
+    } else {
+      index += offset;
+    }
+    list.splice(index, 0, ...rules);
+    config.rules = list;
+    return config;
+  };
+
+  // proxies (objects or names) whose name matches, or doesn't with `exclude`
+  const filterProxies = (proxies, pattern, exclude = false) => {
+    const re = toRegExp(pattern);
+    return (proxies || []).filter((p) => re.test(nameOf(p)) !== exclude);
+  };
+
+  const yaml = Object.freeze({
+    parse: (text) => JSON.parse(__verge_yaml__("parse", String(text))),
+    stringify: (value) => __verge_yaml__("stringify", JSON.stringify(value)),
+  });
+
+  return Object.freeze({
+    version: 1,
+    context: Object.freeze({ ...context }),
+    yaml,
+    filterProxies,
+
+    proxyNames(proxies, pattern) {
+      const list = pattern ? filterProxies(proxies, pattern) : proxies;
+      return (list || []).map(nameOf);
+    },
+
+    findProxy(config, name) {
+      return (config.proxies || []).find((p) => p.name === name);
+    },
+
+    createGroup(name, type, proxies, options = {}) {
+      return { ...options, name, type, proxies: (proxies || []).map(nameOf) };
+    },
+
+    insertRulesBefore(config, marker, rules) {
+      return insertRules(config, marker, rules, 0);
+    },
+
+    insertRulesAfter(config, marker, rules) {
+      return insertRules(config, marker, rules, 1);
+    },
+  });
+}
@@ -1,4 +1,4 @@
-use super::SeqMap;
+use super::{ProxyTransform, SeqMap};
 use crate::{
     config::PrfItem,
     utils::{dirs, help},
@@ -19,6 +19,7 @@ pub enum ChainType {
     Rules(SeqMap),
     Proxies(SeqMap),
     Groups(SeqMap),
+    Transform(ProxyTransform),
 }
 
 #[derive(Debug, Clone)]
@@ -61,6 +62,10 @@ impl From<&PrfItem> for Option<ChainItem> {
                 uid,
                 data: ChainType::Groups(help::read_seq_map(&path).ok()?),
             }),
+            "transform" => Some(ChainItem {
+                uid,
+                data: ChainType::Transform(help::read_yaml(&path).ok()?),
+            }),
             _ => None,
         }
     }
@@ -0,0 +1,294 @@
+use crate::utils::rule::rule_target;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+};
+
+/// policies every config has
+const BUILTIN_POLICIES: [&str; 6] = [
+    "DIRECT",
+    "REJECT",
+    "REJECT-DROP",
+    "PASS",
+    "COMPATIBLE",
+    "GLOBAL",
+];
+
+const PORT_FIELDS: [&str; 5] = [
+    "port",
+    "socks-port",
+    "mixed-port",
+    "redir-port",
+    "tproxy-port",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// the core would refuse the config
+    Error,
+    Warn,
+}
+
+/// A problem of the config and where it is, like `proxy-groups[2].proxies[0]`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub path: String,
+    pub message: String,
+}
+
+struct Linter(Vec<Diagnostic>);
+
+impl Linter {
+    fn error(&mut self, path: String, message: String) {
+        self.0.push(Diagnostic {
+            level: Level::Error,
+            path,
+            message,
+        });
+    }
+
+    fn warn(&mut self, path: String, message: String) {
+        self.0.push(Diagnostic {
+            level: Level::Warn,
+            path,
+            message,
+        });
+    }
+}
+
+fn seq<'a>(config: &'a Mapping, key: &str) -> &'a [Value] {
+    config
+        .get(key)
+        .and_then(Value::as_sequence)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn name_of(item: &Value) -> Option<&str> {
+    item.get("name").and_then(Value::as_str)
+}
+
+/// Check the enhanced config without the core
+/// the references between proxies, groups, providers and rules, and the values the core cannot parse
+pub fn lint_config(config: &Mapping) -> Vec<Diagnostic> {
+    let mut lint = Linter(Vec::new());
+    let proxies = seq(config, "proxies");
+    let groups = seq(config, "proxy-groups");
+    let rules = seq(config, "rules");
+    let providers: HashSet<&str> = config
+        .get("proxy-providers")
+        .and_then(Value::as_mapping)
+        .into_iter()
+        .flat_map(|providers| providers.keys().filter_map(Value::as_str))
+        .collect();
+
+    // every proxy and group name with where it is first defined
+    let mut policies: HashMap<&str, String> = HashMap::new();
+    for (key, items) in [("proxies", proxies), ("proxy-groups", groups)] {
+        for (i, item) in items.iter().enumerate() {
+            let path = format!("{key}[{i}]");
+            let Some(name) = name_of(item) else {
+                lint.error(format!("{path}.name"), "missing name".into());
+                continue;
+            };
+            match policies.get(name) {
+                Some(first) => lint.error(
+                    format!("{path}.name"),
+                    format!("duplicate name `{name}`, also at {first}"),
+                ),
+                None => {
+                    policies.insert(name, path);
+                }
+            }
+        }
+    }
+    let known = |name: &str| policies.contains_key(name) || BUILTIN_POLICIES.contains(&name);
+
+    for (i, proxy) in proxies.iter().enumerate() {
+        if let Some(port) = proxy.get("port") {
+            if !matches!(port_of(port), Some(1..)) {
+                lint.error(
+                    format!("proxies[{i}].port"),
+                    format!("invalid port {}", show(port)),
+                );
+            }
+        }
+    }
+
+    for (i, group) in groups.iter().enumerate() {
+        let path = format!("proxy-groups[{i}]");
+        let members = group.get("proxies").and_then(Value::as_sequence);
+        for (j, member) in members.into_iter().flatten().enumerate() {
+            match member.as_str() {
+                Some(name) if known(name) => {}
+                _ => lint.error(
+                    format!("{path}.proxies[{j}]"),
+                    format!("unknown proxy or group {}", show(member)),
+                ),
+            }
+        }
+        let uses = group.get("use").and_then(Value::as_sequence);
+        for (j, provider) in uses.into_iter().flatten().enumerate() {
+            if !provider.as_str().is_some_and(|p| providers.contains(p)) {
+                lint.error(
+                    format!("{path}.use[{j}]"),
+                    format!("unknown proxy provider {}", show(provider)),
+                );
+            }
+        }
+
+        let include_all = [
+            "include-all",
+            "include-all-proxies",
+            "include-all-providers",
+        ]
+        .iter()
+        .any(|key| group.get(key).and_then(Value::as_bool) == Some(true));
+        if members.is_none_or(Vec::is_empty) && uses.is_none_or(Vec::is_empty) && !include_all {
+            lint.warn(path, "the group has no proxies".into());
+        }
+    }
+
+    for (i, rule) in rules.iter().enumerate() {
+        let path = format!("rules[{i}]");
+        let Some(text) = rule.as_str() else {
+            lint.error(path, format!("invalid rule {}", show(rule)));
+            continue;
+        };
+        let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+        let rtype = parts[0].to_ascii_uppercase();
+
+        // a sub rule points at one of `sub-rules`, not at a policy
+        if rtype != "SUB-RULE" {
+            match rule_target(text) {
+                Some((_, target)) if known(target) => {}
+                Some((_, target)) => lint.error(path.clone(), format!("unknown policy `{target}`")),
+                None => lint.error(path.clone(), "missing policy".into()),
+            }
+        }
+
+        let payload = parts.get(1).copied().unwrap_or_default();
+        match rtype.as_str() {
+            "IP-CIDR" | "IP-CIDR6" | "SRC-IP-CIDR" if !is_cidr(payload) => {
+                lint.error(path, format!("invalid cidr `{payload}`"));
+            }
+            "DST-PORT" | "SRC-PORT" | "IN-PORT" if !is_port_range(payload) => {
+                lint.error(path, format!("invalid port `{payload}`"));
+            }
+            _ => {}
+        }
+    }
+
+    for key in PORT_FIELDS {
+        if let Some(port) = config.get(key) {
+            if port_of(port).is_none() {
+                lint.error(key.into(), format!("invalid port {}", show(port)));
+            }
+        }
+    }
+    let fake_ip_range = config.get("dns").and_then(|dns| dns.get("fake-ip-range"));
+    if let Some(range) = fake_ip_range {
+        if !range.as_str().is_some_and(is_cidr) {
+            lint.error(
+                "dns.fake-ip-range".into(),
+                format!("invalid cidr {}", show(range)),
+            );
+        }
+    }
+
+    lint.0
+}
+
+/// a port as a number or a numeric string
+fn port_of(value: &Value) -> Option<u16> {
+    match value {
+        Value::Number(n) => n.as_u64()?.try_into().ok(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// `80`, `8000-9000` or several of them joined by `/`
+fn is_port_range(text: &str) -> bool {
+    text.split('/').all(|range| {
+        let mut ports = range.splitn(2, '-').map(|port| port.trim().parse::<u16>());
+        ports.all(|port| port.is_ok())
+    })
+}
+
+fn is_cidr(text: &str) -> bool {
+    let Some((ip, prefix)) = text.split_once('/') else {
+        return false;
+    };
+    let Ok(ip) = ip.parse::<IpAddr>() else {
+        return false;
+    };
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    prefix.parse::<u8>().is_ok_and(|prefix| prefix <= max)
+}
+
+fn show(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("`{s}`"),
+        value => serde_yaml::to_string(value)
+            .map(|s| format!("`{}`", s.trim()))
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_config() {
+        let config = r#"
+mixed-port: 70000
+proxies:
+  - {name: hk, type: ss, server: a.com, port: 443}
+  - {name: hk, type: ss, server: b.com, port: "0"}
+proxy-providers:
+  sub: {type: http, url: "https://a.com/sub", path: ./sub.yaml}
+proxy-groups:
+  - {name: Proxy, type: select, proxies: [hk, Auto, jp, DIRECT]}
+  - {name: Auto, type: url-test, use: [sub, other]}
+  - {name: Empty, type: select}
+rules:
+  - DOMAIN,a.com,Proxy
+  - IP-CIDR,10.0.0.0/33,DIRECT,no-resolve
+  - DST-PORT,80/8000-9000,Missing
+  - SRC-PORT,abc,DIRECT
+  - SUB-RULE,(NETWORK,TCP),sub-rule
+  - MATCH,Proxy
+"#;
+        let config: Mapping = serde_yaml::from_str(config).unwrap();
+        let diagnostics = lint_config(&config);
+        let found: Vec<(&str, Level)> = diagnostics
+            .iter()
+            .map(|d| (d.path.as_str(), d.level))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("proxies[1].name", Level::Error),
+                ("proxies[1].port", Level::Error),
+                ("proxy-groups[0].proxies[2]", Level::Error),
+                ("proxy-groups[1].use[1]", Level::Error),
+                ("proxy-groups[2]", Level::Warn),
+                ("rules[1]", Level::Error),
+                ("rules[2]", Level::Error),
+                ("rules[3]", Level::Error),
+                ("mixed-port", Level::Error),
+            ],
+            "{diagnostics:#?}"
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "duplicate name `hk`, also at proxies[0]"
+        );
+        assert_eq!(diagnostics[6].message, "unknown policy `Missing`");
+    }
+}
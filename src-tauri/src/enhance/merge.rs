@@ -1,13 +1,88 @@
 use super::use_lowercase;
 use serde_yaml::{self, Mapping, Value};
 
+/// How a key of a merge file is applied, the override syntax of mihomo
+#[derive(Debug, PartialEq, Eq)]
+enum Directive {
+    /// `key`, mappings are merged, anything else is replaced
+    Merge,
+    /// `+key`, the sequence is put before the original one
+    Prepend,
+    /// `key+`, the sequence is put after the original one
+    Append,
+    /// `<key>` or `key!`, the value is replaced as a whole
+    Replace,
+}
+
+/// `+key` and `key+` only prepend or append a sequence,
+/// keys like `+.example.com` of `hosts` and `nameserver-policy` are kept as they are written
+fn parse_key<'a>(key: &'a str, value: &Value) -> (Directive, &'a str) {
+    let is_seq = value.is_sequence();
+    let stripped = [
+        (
+            Directive::Prepend,
+            key.strip_prefix('+')
+                .filter(|k| is_seq && !k.starts_with('.')),
+        ),
+        (Directive::Append, key.strip_suffix('+').filter(|_| is_seq)),
+        (
+            Directive::Replace,
+            key.strip_prefix('<').and_then(|k| k.strip_suffix('>')),
+        ),
+        (Directive::Replace, key.strip_suffix('!')),
+    ];
+    stripped
+        .into_iter()
+        .find_map(|(directive, k)| Some((directive, k.filter(|k| !k.is_empty())?)))
+        .unwrap_or((Directive::Merge, key))
+}
+
+/// the value with the directives of its nested keys applied
+fn resolve(b: &Value) -> Value {
+    let mut a = Value::Null;
+    deep_merge(&mut a, b);
+    a
+}
+
 fn deep_merge(a: &mut Value, b: &Value) {
     match (a, b) {
         (&mut Value::Mapping(ref mut a), Value::Mapping(b)) => {
             for (k, v) in b {
-                deep_merge(a.entry(k.clone()).or_insert(Value::Null), v);
+                let Some((directive, key)) = k.as_str().map(|k| parse_key(k, v)) else {
+                    deep_merge(a.entry(k.clone()).or_insert(Value::Null), v);
+                    continue;
+                };
+                let key = Value::from(key);
+                match (directive, resolve(v)) {
+                    (Directive::Prepend, Value::Sequence(mut seq)) => {
+                        if let Some(Value::Sequence(origin)) = a.get(&key) {
+                            seq.extend(origin.iter().cloned());
+                        }
+                        a.insert(key, seq.into());
+                    }
+                    (Directive::Append, Value::Sequence(seq)) => {
+                        let mut origin = match a.get(&key) {
+                            Some(Value::Sequence(origin)) => origin.clone(),
+                            _ => vec![],
+                        };
+                        origin.extend(seq);
+                        a.insert(key, origin.into());
+                    }
+                    (Directive::Merge, _) => {
+                        deep_merge(a.entry(key).or_insert(Value::Null), v);
+                    }
+                    // `<key>` or `key!`
+                    (_, value) => {
+                        a.insert(key, value);
+                    }
+                }
             }
         }
+        // a mapping merged into nothing still has its directives applied
+        (a, Value::Mapping(b)) => {
+            *a = Value::Mapping(Mapping::new());
+            deep_merge(a, &Value::Mapping(b.clone()));
+        }
         (a, b) => *a = b.clone(),
     }
 }
@@ -58,3 +133,117 @@ fn test_merge() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_override_syntax() -> anyhow::Result<()> {
+        let config = r"
+    rules:
+      - MATCH,Proxy
+    dns:
+      enable: false
+      nameserver: [1.1.1.1]
+      fallback-filter:
+        geoip: true
+        ipcidr: [240.0.0.0/4]
+    tun:
+      enable: false
+      stack: gvisor
+    proxy-groups:
+      - {name: Proxy, type: select, proxies: [a]}
+  ";
+        let merge = r"
+    +rules:
+      - DOMAIN,a.com,DIRECT
+    rules+:
+      - DOMAIN,z.com,DIRECT
+    dns:
+      enable: true
+      +nameserver: [8.8.8.8]
+      fallback-filter!:
+        geoip: false
+    <tun>:
+      enable: true
+    sniffer:
+      sniff:
+        +tls: [443]
+    proxy-groups!:
+      - {name: Auto, type: url-test, proxies: [a]}
+  ";
+
+        let merge = serde_yaml::from_str::<Mapping>(merge)?;
+        let config = serde_yaml::from_str::<Mapping>(config)?;
+        let config = use_merge(merge, config);
+
+        let expect = r"
+    rules:
+      - DOMAIN,a.com,DIRECT
+      - MATCH,Proxy
+      - DOMAIN,z.com,DIRECT
+    dns:
+      enable: true
+      nameserver: [8.8.8.8, 1.1.1.1]
+      fallback-filter:
+        geoip: false
+    tun:
+      enable: true
+    proxy-groups:
+      - {name: Auto, type: url-test, proxies: [a]}
+    sniffer:
+      sniff:
+        tls: [443]
+  ";
+        assert_eq!(config, serde_yaml::from_str::<Mapping>(expect)?);
+        let seq = Value::Sequence(vec![]);
+        assert_eq!(parse_key("rules", &seq), (Directive::Merge, "rules"));
+        assert_eq!(parse_key("+", &seq), (Directive::Merge, "+"));
+        assert_eq!(parse_key("+rules", &seq), (Directive::Prepend, "rules"));
+        assert_eq!(
+            parse_key("+rules", &Value::Null),
+            (Directive::Merge, "+rules")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_plus_keys_are_not_directives() -> anyhow::Result<()> {
+        let config = r"
+    dns:
+      nameserver-policy:
+        '+.google.cn': 114.114.114.114
+        geosite:cn: [223.5.5.5]
+    hosts:
+      localhost: 127.0.0.1
+  ";
+        let merge = r"
+    dns:
+      nameserver-policy:
+        '+.google.cn': 223.5.5.5
+        '+.example.com': [1.1.1.1, 8.8.8.8]
+    hosts:
+      '+.example.com': 1.2.3.4
+      'mtalk.google.com+': 108.177.125.188
+  ";
+
+        let merge = serde_yaml::from_str::<Mapping>(merge)?;
+        let config = serde_yaml::from_str::<Mapping>(config)?;
+        let config = use_merge(merge, config);
+
+        let expect = r"
+    dns:
+      nameserver-policy:
+        '+.google.cn': 223.5.5.5
+        geosite:cn: [223.5.5.5]
+        '+.example.com': [1.1.1.1, 8.8.8.8]
+    hosts:
+      localhost: 127.0.0.1
+      '+.example.com': 1.2.3.4
+      'mtalk.google.com+': 108.177.125.188
+  ";
+        assert_eq!(config, serde_yaml::from_str::<Mapping>(expect)?);
+        Ok(())
+    }
+}
@@ -1,11 +1,15 @@
+pub mod aggregate;
 mod chain;
 pub mod field;
+pub mod lint;
 mod merge;
 mod script;
 pub mod seq;
+pub mod trace;
+mod transform;
 mod tun;
 
-use self::{chain::*, field::*, merge::*, script::*, seq::*, tun::*};
+use self::{chain::*, field::*, merge::*, script::*, seq::*, trace::*, transform::*, tun::*};
 use crate::{config::Config, utils::tmpl};
 use serde_yaml::Mapping;
 use std::collections::{HashMap, HashSet};
@@ -15,6 +19,18 @@ type ResultLog = Vec<(String, String)>;
 /// Enhance mode
 /// 返回最终订阅、该订阅包含的键、和script执行的结果
 pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
+    enhance_with(&mut Trace::new(false)).await
+}
+
+/// 重新跑一遍增强，记录每一步的变化和耗时
+/// 只返回记录，不影响运行时配置
+pub async fn enhance_trace() -> Vec<TraceStep> {
+    let mut trace = Trace::new(true);
+    enhance_with(&mut trace).await;
+    trace.into_steps()
+}
+
+async fn enhance_with(trace: &mut Trace) -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
     // config.yaml 的订阅
     let clash_config = { Config::clash().latest().0.clone() };
 
@@ -43,6 +59,9 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
         verge.verge_tproxy_enabled.unwrap_or(false)
     };
 
+    // 脚本里 `utils.context` 的内容
+    let script_env = ScriptContext::new(clash_core.clone(), enable_tun);
+
     // 从profiles里拿东西
     let (
         mut config,
@@ -51,9 +70,11 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
         rules_item,
         proxies_item,
         groups_item,
+        transform_item,
         global_merge,
         global_script,
         profile_name,
+        (profile_uid, profile_warnings),
     ) = {
         let profiles = Config::profiles();
         let profiles = profiles.latest();
@@ -99,6 +120,14 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
                 uid: "".into(),
                 data: ChainType::Groups(SeqMap::default()),
             });
+        let transform = profiles
+            .get_item(&profiles.current_transform().unwrap_or_default())
+            .ok()
+            .and_then(<Option<ChainItem>>::from)
+            .unwrap_or_else(|| ChainItem {
+                uid: "".into(),
+                data: ChainType::Transform(ProxyTransform::default()),
+            });
 
         let global_merge = profiles
             .get_item(&"Merge".to_string())
@@ -118,11 +147,16 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
                 data: ChainType::Script(tmpl::ITEM_SCRIPT.into()),
             });
 
-        let name = profiles
+        let current_item = profiles
             .get_item(&profiles.get_current().unwrap_or_default())
-            .ok()
+            .ok();
+        let name = current_item
             .and_then(|item| item.name.clone())
             .unwrap_or_default();
+        // 导入订阅时无法转换的内容
+        let warnings = current_item
+            .and_then(|item| Some((item.uid.clone()?, item.warnings.clone()?)))
+            .unwrap_or_default();
 
         (
             current,
@@ -131,25 +165,38 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
             rules,
             proxies,
             groups,
+            transform,
             global_merge,
             global_script,
             name,
+            warnings,
         )
     };
 
     let mut result_map = HashMap::new(); // 保存脚本日志
+    if !profile_warnings.is_empty() {
+        let logs: ResultLog = profile_warnings
+            .into_iter()
+            .map(|warning| ("warn".into(), warning))
+            .collect();
+        result_map.insert(profile_uid, logs);
+    }
     let mut exists_keys = use_keys(&config); // 保存出现过的keys
+    trace.start(&config);
 
     // 全局Merge和Script
     if let ChainType::Merge(merge) = global_merge.data {
-        exists_keys.extend(use_keys(&merge));
         config = use_merge(merge, config.to_owned());
+        // 合并后再取key，`+rules` 这类覆写语法的key不算
+        exists_keys.extend(use_keys(&config));
     }
+    trace.step("global merge", &global_merge.uid, &config);
 
     if let ChainType::Script(script) = global_script.data {
         let mut logs = vec![];
 
-        match use_script(script, config.to_owned(), profile_name.to_owned()) {
+        let env = script_env.clone();
+        match use_script(script, config.to_owned(), profile_name.to_owned(), env).await {
             Ok((res_config, res_logs)) => {
                 exists_keys.extend(use_keys(&res_config));
                 config = res_config;
@@ -158,31 +205,64 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
             Err(err) => logs.push(("exception".into(), err.to_string())),
         }
 
-        result_map.insert(global_script.uid, logs);
+        result_map.insert(global_script.uid.clone(), logs);
     }
+    trace.step("global script", &global_script.uid, &config);
 
     // 订阅关联的Merge、Script、Rules、Proxies、Groups
     if let ChainType::Rules(rules) = rules_item.data {
-        config = use_seq(rules, config.to_owned(), "rules");
+        let (res_config, warnings) = use_seq(rules, config.to_owned(), "rules");
+        config = res_config;
+        if !warnings.is_empty() {
+            let logs: ResultLog = warnings.into_iter().map(|w| ("warn".into(), w)).collect();
+            result_map.insert(rules_item.uid.clone(), logs);
+        }
     }
+    trace.step("rules", &rules_item.uid, &config);
 
     if let ChainType::Proxies(proxies) = proxies_item.data {
-        config = use_seq(proxies, config.to_owned(), "proxies");
+        let (res_config, warnings) = use_seq(proxies, config.to_owned(), "proxies");
+        config = res_config;
+        if !warnings.is_empty() {
+            let logs: ResultLog = warnings.into_iter().map(|w| ("warn".into(), w)).collect();
+            result_map.insert(proxies_item.uid.clone(), logs);
+        }
+    }
+    trace.step("proxies", &proxies_item.uid, &config);
+
+    // 节点改名或删除后，代理组和规则中的引用一起更新
+    if let ChainType::Transform(transform) = transform_item.data {
+        let (res_config, warnings) = use_transform(transform, config.to_owned());
+        config = res_config;
+        if !warnings.is_empty() {
+            let logs: ResultLog = warnings.into_iter().map(|w| ("warn".into(), w)).collect();
+            result_map.insert(transform_item.uid.clone(), logs);
+        }
     }
+    trace.step("transform", &transform_item.uid, &config);
 
     if let ChainType::Groups(groups) = groups_item.data {
-        config = use_seq(groups, config.to_owned(), "proxy-groups");
+        let (res_config, warnings) = use_seq(groups, config.to_owned(), "proxy-groups");
+        config = res_config;
+        if !warnings.is_empty() {
+            let logs: ResultLog = warnings.into_iter().map(|w| ("warn".into(), w)).collect();
+            result_map.insert(groups_item.uid.clone(), logs);
+        }
     }
+    trace.step("groups", &groups_item.uid, &config);
 
     if let ChainType::Merge(merge) = merge_item.data {
-        exists_keys.extend(use_keys(&merge));
         config = use_merge(merge, config.to_owned());
+        // 合并后再取key，`+rules` 这类覆写语法的key不算
+        exists_keys.extend(use_keys(&config));
     }
+    trace.step("merge", &merge_item.uid, &config);
 
     if let ChainType::Script(script) = script_item.data {
         let mut logs = vec![];
 
-        match use_script(script, config.to_owned(), profile_name.to_owned()) {
+        let env = script_env.clone();
+        match use_script(script, config.to_owned(), profile_name.to_owned(), env).await {
             Ok((res_config, res_logs)) => {
                 exists_keys.extend(use_keys(&res_config));
                 config = res_config;
@@ -191,8 +271,9 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
             Err(err) => logs.push(("exception".into(), err.to_string())),
         }
 
-        result_map.insert(script_item.uid, logs);
+        result_map.insert(script_item.uid.clone(), logs);
     }
+    trace.step("script", &script_item.uid, &config);
 
     // 合并默认的config
     for (key, value) in clash_config.into_iter() {
@@ -231,30 +312,38 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
             config.insert(key, value);
         }
     }
+    trace.step("clash config", "", &config);
 
     // 内建脚本最后跑
     if enable_builtin {
-        ChainItem::builtin()
+        let builtin = ChainItem::builtin()
             .into_iter()
             .filter(|(s, _)| s.is_support(clash_core.as_ref()))
-            .map(|(_, c)| c)
-            .for_each(|item| {
-                log::debug!(target: "app", "run builtin script {}", item.uid);
-                if let ChainType::Script(script) = item.data {
-                    match use_script(script, config.to_owned(), "".to_string()) {
-                        Ok((res_config, _)) => {
-                            config = res_config;
-                        }
-                        Err(err) => {
-                            log::error!(target: "app", "builtin script error `{err}`");
-                        }
+            .map(|(_, c)| c);
+        for item in builtin {
+            log::debug!(target: "app", "run builtin script {}", item.uid);
+            if let ChainType::Script(script) = item.data {
+                let env = script_env.clone();
+                match use_script(script, config.to_owned(), "".to_string(), env).await {
+                    Ok((res_config, _)) => {
+                        config = res_config;
+                    }
+                    Err(err) => {
+                        log::error!(target: "app", "builtin script error `{err}`");
+                        // 内建的修正没有生效，和全局脚本的日志一起显示
+                        let log = format!("builtin script `{}` was skipped, {err}", item.uid);
+                        result_map.insert(item.uid.clone(), vec![("exception".into(), log)]);
                     }
                 }
-            });
+            }
+            trace.step("builtin script", &item.uid, &config);
+        }
     }
 
     config = use_tun(config, enable_tun).await;
+    trace.step("tun", "", &config);
     config = use_sort(config);
+    trace.step("sort", "", &config);
 
     // 应用独立的DNS配置（如果启用）
     if enable_dns_settings {
@@ -275,6 +364,7 @@ pub async fn enhance() -> (Mapping, Vec<String>, HashMap<String, ResultLog>) {
                 }
             }
         }
+        trace.step("dns", "", &config);
     }
 
     let mut exists_set = HashSet::new();
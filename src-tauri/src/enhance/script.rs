@@ -1,15 +1,134 @@
 use super::use_lowercase;
 use anyhow::{Error, Result};
+use boa_engine::{
+    builtins::promise::PromiseState, js_string, object::builtins::JsPromise, Context, JsError,
+    JsNativeError, JsObject, JsResult, JsString, JsValue, Module, Source,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
 use serde_yaml::Mapping;
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-pub fn use_script(
+/// 脚本最长的执行时间
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(10);
+/// 单个循环最多的迭代次数
+const LOOP_ITERATION_LIMIT: u64 = 1_000_000;
+/// 函数最深的递归层数
+const RECURSION_LIMIT: usize = 400;
+/// 执行脚本的线程的栈大小，递归到上限前不能先把栈用完
+const SCRIPT_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// 超时后被丢下、还在执行的脚本
+/// 只拒绝同一个脚本，别的脚本和内建脚本照常执行
+static STRAY_SCRIPTS: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+#[derive(PartialEq, Eq)]
+enum ScriptState {
+    Running,
+    Finished,
+    /// 超时后被丢下，还在执行
+    Abandoned,
+}
+
+/// 脚本通过 `utils.context` 读到的运行环境，只读
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScriptContext {
+    /// 当前的内核
+    pub core: String,
+    pub tun: bool,
+    /// windows | macos | linux
+    pub platform: String,
+}
+
+impl ScriptContext {
+    pub fn new(core: Option<String>, tun: bool) -> Self {
+        Self {
+            core: core.unwrap_or_default(),
+            tun,
+            platform: std::env::consts::OS.into(),
+        }
+    }
+}
+
+/// 在单独的线程里执行脚本，不阻塞异步运行时
+/// 超时、循环次数或递归层数超限都会返回错误，由调用方保留原来的配置
+/// 同一个脚本之前超时后还在执行时，不再为它开新的线程
+pub async fn use_script(
+    script: String,
+    config: Mapping,
+    name: String,
+    env: ScriptContext,
+) -> Result<(Mapping, Vec<(String, String)>)> {
+    use_script_timeout(script, config, name, env, SCRIPT_TIMEOUT).await
+}
+
+async fn use_script_timeout(
     script: String,
     config: Mapping,
     name: String,
+    env: ScriptContext,
+    timeout: Duration,
+) -> Result<(Mapping, Vec<(String, String)>)> {
+    // boa没法从外部中断，嵌套的循环每层都不超限时只能等超时，线程会被丢下一直执行
+    // 同一个脚本不再开新线程，免得每次增强都多一个占满CPU的线程
+    if STRAY_SCRIPTS.lock().unwrap().contains(&script) {
+        anyhow::bail!("the script timed out earlier and is still running");
+    }
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    // 脚本线程是否已经结束、是否已经被丢下，都在持有 `STRAY_SCRIPTS` 的锁时读写
+    let state = Arc::new(Mutex::new(ScriptState::Running));
+    let thread_state = state.clone();
+    let key = script.clone();
+    std::thread::Builder::new()
+        .name("enhance-script".into())
+        .stack_size(SCRIPT_STACK_SIZE)
+        .spawn(move || {
+            let result = run_script(&key, config, name, env);
+            let mut stray = STRAY_SCRIPTS.lock().unwrap();
+            let state =
+                std::mem::replace(&mut *thread_state.lock().unwrap(), ScriptState::Finished);
+            if state == ScriptState::Abandoned {
+                stray.remove(&key);
+            }
+            let _ = tx.send(result);
+        })?;
+
+    match tokio::time::timeout(timeout, rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => anyhow::bail!("script thread exited unexpectedly"),
+        Err(_) => {
+            let mut stray = STRAY_SCRIPTS.lock().unwrap();
+            let mut state = state.lock().unwrap();
+            // 刚好在超时后结束的线程不算
+            if *state == ScriptState::Running {
+                *state = ScriptState::Abandoned;
+                stray.insert(script);
+            }
+            anyhow::bail!("script timed out after {}s", timeout.as_secs_f32())
+        }
+    }
+}
+
+fn run_script(
+    script: &str,
+    config: Mapping,
+    name: String,
+    env: ScriptContext,
 ) -> Result<(Mapping, Vec<(String, String)>)> {
-    use boa_engine::{native_function::NativeFunction, Context, JsValue, Source};
-    use std::sync::{Arc, Mutex};
+    use boa_engine::native_function::NativeFunction;
     let mut context = Context::default();
+    context
+        .runtime_limits_mut()
+        .set_loop_iteration_limit(LOOP_ITERATION_LIMIT);
+    context
+        .runtime_limits_mut()
+        .set_recursion_limit(RECURSION_LIMIT);
 
     let outputs = Arc::new(Mutex::new(vec![]));
 
@@ -31,6 +150,11 @@ pub fn use_script(
             ),
         );
     }
+    let _ = context.register_global_builtin_callable(
+        "__verge_yaml__".into(),
+        2,
+        NativeFunction::from_fn_ptr(use_yaml),
+    );
     let _ = context.eval(Source::from_bytes(
         r#"var console = Object.freeze({
         log(data){__verge_log__("log",JSON.stringify(data))}, 
@@ -40,45 +164,143 @@ pub fn use_script(
       });"#,
     ));
 
+    // 注入只读的 `utils`，脚本里不能改也不能重新赋值
+    context
+        .eval(Source::from_bytes(include_str!("./builtin/utils.js")))
+        .map_err(|err| js_error(err, &mut context))?;
+    let env = serde_json::to_string(&env)?;
+    context
+        .eval(Source::from_bytes(&format!(
+            r#"Object.defineProperty(globalThis, "utils", {{ value: __verge_utils__({env}) }});"#
+        )))
+        .map_err(|err| js_error(err, &mut context))?;
+
     let config = use_lowercase(config.clone());
-    let config_str = serde_json::to_string(&config)?;
-
-    let code = format!(
-        r#"try{{
-        {script};
-        JSON.stringify(main({config_str},'{name}')||'')
-      }} catch(err) {{
-        `__error_flag__ ${{err.toString()}}`
-      }}"#
-    );
-    if let Ok(result) = context.eval(Source::from_bytes(code.as_str())) {
-        if !result.is_string() {
-            anyhow::bail!("main function should return object");
-        }
-        let result = result.to_string(&mut context).unwrap();
-        let result = result.to_std_string().unwrap();
-        if result.starts_with("__error_flag__") {
-            anyhow::bail!(result[15..].to_owned());
-        }
-        if result == "\"\"" {
-            anyhow::bail!("main function should return object");
-        }
-        let res: Result<Mapping, Error> = Ok(serde_json::from_str::<Mapping>(result.as_str())?);
-        let mut out = outputs.lock().unwrap();
-        match res {
-            Ok(config) => Ok((use_lowercase(config), out.to_vec())),
-            Err(err) => {
-                out.push(("exception".into(), err.to_string()));
-                Ok((config, out.to_vec()))
-            }
+    // 配置和订阅名都作为JS值传给main，不拼进源码
+    let args = [
+        JsValue::from_json(&serde_json::to_value(&config)?, &mut context)
+            .map_err(|err| js_error(err, &mut context))?,
+        JsString::from(name.as_str()).into(),
+    ];
+
+    // 脚本抛出的错误和超出运行限制的错误都从这里返回
+    let result = load_main(script, &mut context)
+        .and_then(|main| main.call(&JsValue::undefined(), &args, &mut context))
+        .and_then(|result| settle(result, &mut context))
+        .map_err(|err| js_error(err, &mut context))?;
+    if !result.is_object() {
+        anyhow::bail!("main function should return object");
+    }
+    let result = result
+        .to_json(&mut context)
+        .map_err(|err| js_error(err, &mut context))?;
+    let res: Result<Mapping, Error> = Ok(serde_json::from_value::<Mapping>(result)?);
+    let mut out = outputs.lock().unwrap();
+    match res {
+        Ok(config) => Ok((use_lowercase(config), out.to_vec())),
+        Err(err) => {
+            out.push(("exception".into(), err.to_string()));
+            Ok((config, out.to_vec()))
         }
+    }
+}
+
+/// 加载脚本并找到入口函数
+/// 有 `import`/`export` 的按ES模块加载，入口是 `export default` 或导出的 `main`
+/// 其他的按普通脚本执行，入口是 `module.exports` 或全局的 `main`
+fn load_main(script: &str, context: &mut Context) -> JsResult<JsObject> {
+    static ESM: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\s*(import|export)\b").unwrap());
+
+    let (exports, fallback) = if ESM.is_match(script) {
+        let module = Module::parse(Source::from_bytes(script), None, context)?;
+        let promise = module.load_link_evaluate(context);
+        settle(promise.into(), context)?;
+        (module.namespace(context).into(), JsValue::undefined())
     } else {
-        anyhow::bail!("main function should return object");
+        context.eval(Source::from_bytes(
+            "var module = { exports: {} }; var exports = module.exports;",
+        ))?;
+        context.eval(Source::from_bytes(script))?;
+        let global = context.global_object();
+        let exports = global.get(js_string!("module"), context)?;
+        let exports = match exports.as_object() {
+            Some(module) => module.get(js_string!("exports"), context)?,
+            None => JsValue::undefined(),
+        };
+        (exports, global.get(js_string!("main"), context)?)
+    };
+
+    let mut candidates = vec![exports.clone()];
+    if let Some(exports) = exports.as_object() {
+        candidates.push(exports.get(js_string!("default"), context)?);
+        candidates.push(exports.get(js_string!("main"), context)?);
+    }
+    candidates.push(fallback);
+    candidates
+        .into_iter()
+        .find_map(|value| value.as_callable().cloned())
+        .ok_or_else(|| {
+            JsNativeError::typ()
+                .with_message("main function is not defined")
+                .into()
+        })
+}
+
+/// 跑完任务队列，Promise取它的结果，其他值原样返回
+fn settle(value: JsValue, context: &mut Context) -> JsResult<JsValue> {
+    context.run_jobs();
+    let Some(promise) = value
+        .as_object()
+        .cloned()
+        .and_then(|o| JsPromise::from_object(o).ok())
+    else {
+        return Ok(value);
+    };
+    match promise.state() {
+        PromiseState::Fulfilled(value) => Ok(value),
+        PromiseState::Rejected(err) => Err(JsError::from_opaque(err)),
+        PromiseState::Pending => Err(JsNativeError::error()
+            .with_message("main function returned a promise that never settled")
+            .into()),
     }
 }
 
-#[test]
-fn test_script() {
+/// 脚本抛出的值按 `String(err)` 显示，引擎自己的错误（如超出运行限制）直接显示
+fn js_error(err: JsError, context: &mut Context) -> Error {
+    if err.as_native().is_none() {
+        if let Ok(message) = err.to_opaque(context).to_string(context) {
+            return anyhow::anyhow!(message.to_std_string_escaped());
+        }
+    }
+    anyhow::anyhow!("{err}")
+}
+
+/// `utils.yaml` 的实现，`parse` 把YAML转成JSON，`stringify` 把JSON转成YAML
+fn use_yaml(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let arg = |i: usize, context: &mut Context| -> JsResult<String> {
+        let value = args.get(i).cloned().unwrap_or_default();
+        Ok(value.to_string(context)?.to_std_string_escaped())
+    };
+    let op = arg(0, context)?;
+    let text = arg(1, context)?;
+    let result = match op.as_str() {
+        "parse" => serde_yaml::from_str::<serde_json::Value>(&text)
+            .map_err(|err| err.to_string())
+            .and_then(|value| serde_json::to_string(&value).map_err(|err| err.to_string())),
+        _ => serde_json::from_str::<serde_yaml::Value>(&text)
+            .map_err(|err| err.to_string())
+            .and_then(|value| serde_yaml::to_string(&value).map_err(|err| err.to_string())),
+    };
+    match result {
+        Ok(result) => Ok(JsString::from(result.as_str()).into()),
+        Err(err) => Err(JsNativeError::error()
+            .with_message(format!("yaml {op} failed: {err}"))
+            .into()),
+    }
+}
+
+#[tokio::test]
+async fn test_script() {
     let script = r#"
     function main(config) {
       if (Array.isArray(config.rules)) {
@@ -101,9 +323,153 @@ fn test_script() {
   "#;
 
     let config = serde_yaml::from_str(config).unwrap();
-    let (config, results) = use_script(script.into(), config, "".to_string()).unwrap();
+    let (config, results) = use_script(
+        script.into(),
+        config,
+        "".to_string(),
+        ScriptContext::default(),
+    )
+    .await
+    .unwrap();
 
     let _ = serde_yaml::to_string(&config).unwrap();
 
     dbg!(results);
 }
+
+#[tokio::test]
+async fn test_script_limits() {
+    let run = |script: &str, timeout: u64| {
+        use_script_timeout(
+            script.into(),
+            Mapping::new(),
+            "".into(),
+            ScriptContext::default(),
+            Duration::from_millis(timeout),
+        )
+    };
+
+    let err = run("function main(config) { while (true) {} }", 10_000)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("loop iteration limit"), "{err}");
+
+    let err = run("function main(config) { return main(config) }", 10_000)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("RuntimeLimit"), "{err}");
+
+    // 每层循环都没超限，只能靠超时，循环有尽头，测试结束前线程会自己退出
+    let script = r#"
+    function spin() { for (let i = 0; i < 1000; i++) {} }
+    function main(config) { for (let i = 0; i < 200; i++) spin(); return config }
+    "#;
+    let err = run(script, 10).await.unwrap_err();
+    assert!(err.to_string().contains("timed out"), "{err}");
+
+    // 超时的线程还在执行，同一个脚本不再开新的，别的脚本照常执行
+    let err = run(script, 10_000).await.unwrap_err();
+    assert!(err.to_string().contains("still running"), "{err}");
+    let other = "function main(config) { return config }";
+    assert!(run(other, 10_000).await.is_ok());
+
+    // 线程退出后同一个脚本又能执行
+    let mut tries = 0;
+    while STRAY_SCRIPTS.lock().unwrap().contains(script) {
+        assert!(tries < 600, "the script thread never exited");
+        tries += 1;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(run(script, 60_000).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_script_utils() {
+    let script = r#"
+    function main(config) {
+      utils.version = 0;
+      const hk = utils.filterProxies(config.proxies, /^HK/);
+      config["proxy-groups"] = [
+        utils.createGroup("HK", "url-test", hk, { interval: 300 }),
+      ];
+      utils.insertRulesBefore(config, "MATCH", ["DOMAIN,b.com,HK"]);
+      utils.insertRulesAfter(config, "DOMAIN,a.com", ["DOMAIN,c.com,HK"]);
+      config.extra = utils.yaml.parse(utils.yaml.stringify({ a: [1] }));
+      config.found = utils.findProxy(config, "JP 1").server;
+      config.env = [utils.version, utils.context.tun, utils.context.core];
+      return config;
+    }
+  "#;
+
+    let config = r#"
+    proxies:
+      - {name: HK 1, server: a}
+      - {name: JP 1, server: b}
+      - {name: HK 2, server: c}
+    rules:
+      - DOMAIN,a.com,DIRECT
+      - MATCH,DIRECT
+  "#;
+
+    let config = serde_yaml::from_str(config).unwrap();
+    let env = ScriptContext::new(Some("verge-mihomo".into()), true);
+    let (config, _) = use_script(script.into(), config, "".into(), env)
+        .await
+        .unwrap();
+
+    let expected = r#"
+    proxy-groups:
+      - {interval: 300, name: HK, type: url-test, proxies: [HK 1, HK 2]}
+    rules:
+      - DOMAIN,a.com,DIRECT
+      - DOMAIN,c.com,HK
+      - DOMAIN,b.com,HK
+      - MATCH,DIRECT
+    extra: {a: [1]}
+    found: b
+    env: [1, true, verge-mihomo]
+  "#;
+    let expected: Mapping = serde_yaml::from_str(expected).unwrap();
+    for (key, value) in expected {
+        assert_eq!(config.get(&key), Some(&value), "{key:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_script_modules() {
+    let run = |script: &str, name: &str| {
+        use_script(
+            script.into(),
+            Mapping::new(),
+            name.into(),
+            ScriptContext::default(),
+        )
+    };
+
+    let script = r#"
+    const prefix = await Promise.resolve("esm");
+    export default async function (config, profileName) {
+      config.name = `${prefix}:${await Promise.resolve(profileName)}`;
+      return config;
+    }
+  "#;
+    let (config, _) = run(script, "it's mine").await.unwrap();
+    assert_eq!(config["name"], "esm:it's mine");
+
+    let script = r#"
+    module.exports = async (config, profileName) => ({ ...config, name: profileName });
+  "#;
+    let (config, _) = run(script, "cjs").await.unwrap();
+    assert_eq!(config["name"], "cjs");
+
+    let script = r#"
+    export async function main(config) {
+      throw new Error("broken");
+    }
+  "#;
+    let err = run(script, "").await.unwrap_err();
+    assert_eq!(err.to_string(), "Error: broken");
+
+    let err = run("function other() {}", "").await.unwrap_err();
+    assert!(err.to_string().contains("main function"), "{err}");
+}
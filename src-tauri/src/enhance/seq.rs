@@ -1,50 +1,130 @@
+use crate::utils::rule::rule_target;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_yaml::{Mapping, Sequence, Value};
+use std::collections::HashSet;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SeqMap {
     pub prepend: Sequence,
     pub append: Sequence,
+    /// exact names, `*` `?` globs or `/regex/`
+    /// rules are matched as a whole, proxies and groups by `name`
     pub delete: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replace: Vec<SeqReplace>,
 }
 
-pub fn use_seq(seq: SeqMap, mut config: Mapping, field: &str) -> Mapping {
+/// Patch the items matching `pattern` in place
+/// a mapping is merged into proxies and groups, a rule is replaced by the string
+/// where a `/regex/` pattern may refer to its captures as `$1`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeqReplace {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    pub with: Value,
+}
+
+enum Pattern {
+    Exact(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn new(pattern: &str) -> Self {
+        let regex = match pattern.strip_prefix('/').and_then(|p| p.strip_suffix('/')) {
+            Some(regex) => Regex::new(regex),
+            None if pattern.contains(['*', '?']) => {
+                let glob = regex::escape(pattern)
+                    .replace(r"\*", ".*")
+                    .replace(r"\?", ".");
+                Regex::new(&format!("^{glob}$"))
+            }
+            None => return Pattern::Exact(pattern.into()),
+        };
+        match regex {
+            Ok(regex) => Pattern::Regex(regex),
+            Err(err) => {
+                log::warn!(target: "app", "invalid pattern `{pattern}`, matched exactly, {err}");
+                Pattern::Exact(pattern.into())
+            }
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Pattern::Exact(exact) => exact == text,
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// the rule itself, or the `name` of a proxy or group
+fn key_of(item: &Value) -> Option<&str> {
+    match item {
+        Value::String(s) => Some(s),
+        Value::Mapping(m) => m.get("name").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+/// Apply the prepend, append, delete and replace of `seq` to `config[field]`
+/// returns the config and the rules dropped with the deleted proxies or groups
+pub fn use_seq(seq: SeqMap, mut config: Mapping, field: &str) -> (Mapping, Vec<String>) {
     let SeqMap {
         prepend,
         append,
         delete,
+        replace,
     } = seq;
+    let delete: Vec<Pattern> = delete.iter().map(|p| Pattern::new(p)).collect();
+    let replace: Vec<(Pattern, Value)> = replace
+        .into_iter()
+        .map(|r| (Pattern::new(&r.pattern), r.with))
+        .collect();
 
     let mut new_seq = Sequence::new();
     new_seq.extend(prepend);
 
+    // names of the deleted proxies or groups
+    let mut deleted = HashSet::new();
+    let mut warnings = Vec::new();
     if let Some(Value::Sequence(origin)) = config.get(field) {
-        // Filter out deleted items
-        let filtered: Sequence = origin
-            .iter()
-            .filter(|item| {
-                if let Value::String(s) = item {
-                    !delete.contains(s)
-                } else if let Value::Mapping(m) = item {
-                    if let Some(Value::String(name)) = m.get("name") {
-                        !delete.contains(name)
-                    } else {
-                        true
-                    }
-                } else {
-                    true
+        for item in origin {
+            let key = key_of(item);
+            if let Some(key) = key.filter(|key| delete.iter().any(|p| p.is_match(key))) {
+                deleted.insert(key.to_string());
+                continue;
+            }
+            let mut item = item.clone();
+            for (pattern, with) in replace.iter() {
+                if !key_of(&item).is_some_and(|key| pattern.is_match(key)) {
+                    continue;
                 }
-            })
-            .cloned()
-            .collect();
-        new_seq.extend(filtered);
+                item = match (item, with) {
+                    (Value::Mapping(mut map), Value::Mapping(patch)) => {
+                        for (k, v) in patch {
+                            map.insert(k.clone(), v.clone());
+                        }
+                        Value::Mapping(map)
+                    }
+                    (Value::String(rule), Value::String(with)) => match pattern {
+                        Pattern::Regex(regex) => regex.replace_all(&rule, with.as_str()).into(),
+                        Pattern::Exact(_) => with.clone().into(),
+                    },
+                    (item, _) => item,
+                };
+            }
+            new_seq.push(item);
+        }
     }
 
     new_seq.extend(append);
     config.insert(Value::String(field.into()), Value::Sequence(new_seq));
 
-    // If this is proxies field, we also need to filter proxy-groups
-    if field == "proxies" {
+    // Deleted proxies or groups are also removed from the proxy-groups,
+    // and the rules pointing at them are removed
+    if field == "proxies" || field == "proxy-groups" {
         if let Some(Value::Sequence(groups)) = config.get_mut("proxy-groups") {
             let mut new_groups = Sequence::new();
             for group in groups {
@@ -55,7 +135,7 @@ pub fn use_seq(seq: SeqMap, mut config: Mapping, field: &str) -> Mapping {
                             .iter()
                             .filter(|p| {
                                 if let Value::String(name) = p {
-                                    !delete.contains(name)
+                                    !deleted.contains(name)
                                 } else {
                                     true
                                 }
@@ -77,9 +157,24 @@ pub fn use_seq(seq: SeqMap, mut config: Mapping, field: &str) -> Mapping {
                 Value::Sequence(new_groups),
             );
         }
+
+        if let Some(Value::Sequence(rules)) = config.get_mut("rules") {
+            rules.retain(|rule| {
+                let Some(text) = rule.as_str() else {
+                    return true;
+                };
+                match rule_target(text) {
+                    Some((_, target)) if deleted.contains(target) => {
+                        warnings.push(format!("dropped the rule `{text}`, `{target}` was deleted"));
+                        false
+                    }
+                    _ => true,
+                }
+            });
+        }
     }
 
-    config
+    (config, warnings)
 }
 
 #[cfg(test)]
@@ -113,9 +208,10 @@ proxy-groups:
             prepend: Sequence::new(),
             append: Sequence::new(),
             delete: vec!["proxy1".to_string()],
+            replace: vec![],
         };
 
-        config = use_seq(seq, config, "proxies");
+        (config, _) = use_seq(seq, config, "proxies");
 
         // Check if proxy1 is removed from proxies
         let proxies = config.get("proxies").unwrap().as_sequence().unwrap();
@@ -152,4 +248,72 @@ proxy-groups:
         assert_eq!(group1_proxies[0].as_str().unwrap(), "proxy2");
         assert_eq!(group2_proxies.len(), 0);
     }
+
+    #[test]
+    fn test_delete_and_replace_patterns() {
+        let config_str = r#"
+proxies:
+  - {name: "HK 01", type: ss}
+  - {name: "Expire 2030-01-01", type: ss}
+  - {name: "JP 01", type: ss}
+proxy-groups:
+  - {name: Proxy, type: select, proxies: ["HK 01", "Expire 2030-01-01", "JP 01"]}
+rules:
+  - "DOMAIN-SUFFIX,a.ads.com,REJECT"
+  - "DOMAIN-SUFFIX,b.ads.net,REJECT"
+  - "IP-CIDR,1.1.1.1/32,Expire 2030-01-01,no-resolve"
+  - "DOMAIN,example.com,Proxy"
+  - "MATCH,Proxy"
+"#;
+        let config: Mapping = serde_yaml::from_str(config_str).unwrap();
+
+        let seq: SeqMap = serde_yaml::from_str(
+            r#"
+prepend: []
+append: []
+delete: ["*Expire*"]
+replace:
+  - {match: "/^HK/", with: {udp: true}}
+"#,
+        )
+        .unwrap();
+        let (config, warnings) = use_seq(seq, config, "proxies");
+        let proxies = config["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 2);
+        assert_eq!(proxies[0]["udp"], Value::Bool(true));
+        assert!(proxies[1].get("udp").is_none());
+        assert_eq!(
+            config["proxy-groups"][0]["proxies"]
+                .as_sequence()
+                .unwrap()
+                .len(),
+            2
+        );
+        // the rule pointing at the deleted proxy is removed
+        assert_eq!(config["rules"].as_sequence().unwrap().len(), 4);
+        assert_eq!(
+            warnings,
+            ["dropped the rule `IP-CIDR,1.1.1.1/32,Expire 2030-01-01,no-resolve`, `Expire 2030-01-01` was deleted"]
+        );
+
+        let seq: SeqMap = serde_yaml::from_str(
+            r#"
+prepend: []
+append: []
+delete: ["DOMAIN-SUFFIX,*.ads.*"]
+replace:
+  - {match: "/^(DOMAIN,.*),Proxy$/", with: "$1,DIRECT"}
+"#,
+        )
+        .unwrap();
+        let (config, warnings) = use_seq(seq, config, "rules");
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let rules: Vec<&str> = config["rules"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|r| r.as_str().unwrap())
+            .collect();
+        assert_eq!(rules, ["DOMAIN,example.com,DIRECT", "MATCH,Proxy"]);
+    }
 }
@@ -0,0 +1,176 @@
+use crate::config::PrfChanges;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::{collections::HashMap, time::Instant};
+
+/// What one step of `enhance()` changed and how long it took
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceStep {
+    /// like `global merge` or `tun`
+    pub step: String,
+    /// the chain item of the step, if it has one
+    pub uid: Option<String>,
+    /// microseconds the step took
+    pub elapsed: u64,
+    /// top level keys
+    pub keys: PrfChanges,
+    pub proxies: PrfChanges,
+    pub groups: PrfChanges,
+    /// rules have no names, `changed` stays empty
+    pub rules: PrfChanges,
+}
+
+/// Records a `TraceStep` after every step of `enhance()`
+/// A disabled trace records nothing and doesn't keep a copy of the config
+pub struct Trace {
+    steps: Vec<TraceStep>,
+    last: Option<Mapping>,
+    enabled: bool,
+    at: Instant,
+}
+
+impl Trace {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            steps: vec![],
+            last: None,
+            enabled,
+            at: Instant::now(),
+        }
+    }
+
+    /// the config before the first step
+    pub fn start(&mut self, config: &Mapping) {
+        if self.enabled {
+            self.last = Some(config.clone());
+            self.at = Instant::now();
+        }
+    }
+
+    pub fn step(&mut self, step: &str, uid: &str, config: &Mapping) {
+        if !self.enabled {
+            return;
+        }
+        let elapsed = self.at.elapsed().as_micros() as u64;
+        let last = self.last.replace(config.clone()).unwrap_or_default();
+        self.steps.push(TraceStep {
+            step: step.into(),
+            uid: (!uid.is_empty()).then(|| uid.into()),
+            elapsed,
+            keys: key_changes(&last, config),
+            proxies: PrfChanges::new(&last, config, "proxies"),
+            groups: PrfChanges::new(&last, config, "proxy-groups"),
+            rules: rule_changes(&last, config),
+        });
+        self.at = Instant::now();
+    }
+
+    pub fn into_steps(self) -> Vec<TraceStep> {
+        self.steps
+    }
+}
+
+fn key_changes(old: &Mapping, new: &Mapping) -> PrfChanges {
+    let name = |key: &Value| key.as_str().unwrap_or_default().to_string();
+    let mut changes = PrfChanges::default();
+    for (key, value) in new {
+        match old.get(key) {
+            None => changes.added.push(name(key)),
+            Some(old_value) if old_value != value => changes.changed.push(name(key)),
+            _ => {}
+        }
+    }
+    for key in old.keys() {
+        if !new.contains_key(key) {
+            changes.removed.push(name(key));
+        }
+    }
+    changes
+}
+
+/// rules compared as a multiset, moving a rule is not a change
+fn rule_changes(old: &Mapping, new: &Mapping) -> PrfChanges {
+    let rules = |config: &Mapping| -> Vec<String> {
+        config
+            .get("rules")
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(|rule| rule.as_str().map(String::from))
+            .collect()
+    };
+    let (old, new) = (rules(old), rules(new));
+
+    let mut counts: HashMap<&str, isize> = HashMap::new();
+    for rule in &old {
+        *counts.entry(rule).or_default() -= 1;
+    }
+    for rule in &new {
+        *counts.entry(rule).or_default() += 1;
+    }
+
+    let mut changes = PrfChanges::default();
+    for rule in &new {
+        if let Some(count) = counts.get_mut(rule.as_str()).filter(|count| **count > 0) {
+            *count -= 1;
+            changes.added.push(rule.clone());
+        }
+    }
+    for rule in &old {
+        if let Some(count) = counts.get_mut(rule.as_str()).filter(|count| **count < 0) {
+            *count += 1;
+            changes.removed.push(rule.clone());
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace() {
+        let old = r#"
+mode: rule
+dns: {enable: false}
+proxies:
+  - {name: a, type: ss, server: a.com, port: 1}
+rules: ["DOMAIN,a.com,DIRECT", "DOMAIN,a.com,DIRECT", "MATCH,DIRECT"]
+"#;
+        let new = r#"
+dns: {enable: true}
+tun: {enable: true}
+proxies:
+  - {name: a, type: ss, server: a.com, port: 1}
+  - {name: b, type: ss, server: b.com, port: 1}
+rules: ["MATCH,DIRECT", "DOMAIN,a.com,DIRECT", "DOMAIN,b.com,DIRECT"]
+"#;
+        let old: Mapping = serde_yaml::from_str(old).unwrap();
+        let new: Mapping = serde_yaml::from_str(new).unwrap();
+
+        let mut trace = Trace::new(true);
+        trace.start(&old);
+        trace.step("merge", "m1", &new);
+        trace.step("sort", "", &new);
+        let steps = trace.into_steps();
+
+        let step = &steps[0];
+        assert_eq!(step.uid.as_deref(), Some("m1"));
+        assert_eq!(step.keys.added, ["tun"]);
+        assert_eq!(step.keys.removed, ["mode"]);
+        assert_eq!(step.keys.changed, ["dns", "proxies", "rules"]);
+        assert_eq!(step.proxies.added, ["b"]);
+        assert_eq!(step.rules.added, ["DOMAIN,b.com,DIRECT"]);
+        assert_eq!(step.rules.removed, ["DOMAIN,a.com,DIRECT"]);
+
+        let step = &steps[1];
+        assert_eq!(step.uid, None);
+        assert_eq!(step.keys, PrfChanges::default());
+
+        let mut trace = Trace::new(false);
+        trace.start(&old);
+        trace.step("merge", "m1", &new);
+        assert!(trace.into_steps().is_empty());
+    }
+}
@@ -0,0 +1,390 @@
+use crate::utils::rule::rule_target;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Sequence, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Filter, rename and group the proxies of a profile
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ProxyTransform {
+    /// keep only the proxies whose name matches one of the regexes, all when empty
+    pub include: Vec<String>,
+    /// drop the proxies whose name matches one of the regexes
+    pub exclude: Vec<String>,
+    /// applied in order, `replace` may refer to the captures as `$1` or `${name}`
+    pub rename: Vec<Rename>,
+    /// prefix the names with the flag of the detected region
+    pub flag: bool,
+    /// generate a group for every region that has proxies
+    pub region_groups: Option<RegionGroups>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rename {
+    pub pattern: String,
+    pub replace: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegionGroups {
+    /// `url-test` or `fallback`
+    #[serde(rename = "type")]
+    pub gtype: String,
+    pub url: String,
+    pub interval: u64,
+    /// region codes like `HK`, all detected regions when empty
+    pub regions: Vec<String>,
+}
+
+impl Default for RegionGroups {
+    fn default() -> Self {
+        Self {
+            gtype: "url-test".into(),
+            url: "http://www.gstatic.com/generate_204".into(),
+            interval: 300,
+            regions: vec![],
+        }
+    }
+}
+
+/// region code and the keywords of proxy names in that region
+/// the code alone only matches when it is not part of a longer word
+const REGIONS: [(&str, &str); 16] = [
+    ("HK", "港|(?i:hong ?kong)"),
+    ("TW", "台|(?i:taiwan)"),
+    ("JP", "日本|东京|大阪|(?i:japan|tokyo|osaka)"),
+    ("SG", "新加坡|狮城|(?i:singapore)"),
+    ("KR", "韩|首尔|(?i:korea|seoul)"),
+    (
+        "US",
+        "美国|(?i:united states|america|los angeles|san jose|seattle)",
+    ),
+    ("GB", "英国|伦敦|UK|(?i:united kingdom|britain|london)"),
+    ("DE", "德国|(?i:germany|frankfurt)"),
+    ("FR", "法国|(?i:france|paris)"),
+    ("NL", "荷兰|(?i:netherlands|amsterdam)"),
+    ("CA", "加拿大|(?i:canada)"),
+    ("AU", "澳大利亚|澳洲|(?i:australia|sydney)"),
+    ("RU", "俄罗斯|(?i:russia|moscow)"),
+    ("IN", "印度|(?i:india|mumbai)"),
+    ("TR", "土耳其|(?i:turkey|istanbul)"),
+    ("MY", "马来|(?i:malaysia)"),
+];
+
+static REGION_REGEXES: Lazy<Vec<(&str, Regex)>> = Lazy::new(|| {
+    REGIONS
+        .iter()
+        .map(|(code, keywords)| {
+            let pattern = format!(
+                "(?:^|[^A-Za-z]){code}(?:[^A-Za-z]|$)|{keywords}|{}",
+                flag(code)
+            );
+            (*code, Regex::new(&pattern).unwrap())
+        })
+        .collect()
+});
+
+/// the flag emoji of a two letter region code
+fn flag(code: &str) -> String {
+    code.chars()
+        .filter_map(|c| char::from_u32(0x1F1E6 + (c as u32).checked_sub('A' as u32)?))
+        .collect()
+}
+
+fn has_flag(name: &str) -> bool {
+    name.chars()
+        .any(|c| ('\u{1F1E6}'..='\u{1F1FF}').contains(&c))
+}
+
+fn region_of(name: &str) -> Option<&'static str> {
+    REGION_REGEXES
+        .iter()
+        .find(|(_, regex)| regex.is_match(name))
+        .map(|(code, _)| *code)
+}
+
+/// Apply the transform to the proxies of `config`
+/// the references in `proxy-groups` and `rules` follow the renamed and dropped proxies
+/// returns the config and the patterns that could not be used
+pub fn use_transform(transform: ProxyTransform, mut config: Mapping) -> (Mapping, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut compile = |patterns: &[String]| -> Vec<Regex> {
+        patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(err) => {
+                    warnings.push(format!("invalid regex `{pattern}`: {err}"));
+                    None
+                }
+            })
+            .collect()
+    };
+    let include = compile(&transform.include);
+    let exclude = compile(&transform.exclude);
+    let renames: Vec<(Regex, String)> = transform
+        .rename
+        .iter()
+        .filter_map(|rename| {
+            let regex = compile(std::slice::from_ref(&rename.pattern)).pop()?;
+            Some((regex, rename.replace.clone()))
+        })
+        .collect();
+
+    let Some(Value::Sequence(origin)) = config.get("proxies") else {
+        return (config, warnings);
+    };
+
+    let mut renamed: HashMap<String, String> = HashMap::new();
+    let mut dropped: HashSet<String> = HashSet::new();
+    let mut taken: HashSet<String> = HashSet::new();
+    // a new name must not take the name of a group or of another proxy
+    let reserved: HashSet<&str> = origin
+        .iter()
+        .chain(match config.get("proxy-groups") {
+            Some(Value::Sequence(groups)) => groups.as_slice(),
+            _ => &[],
+        })
+        .filter_map(|item| item.get("name").and_then(Value::as_str))
+        .collect();
+    let mut proxies = Sequence::new();
+    let mut regions: Vec<(&str, Vec<String>)> = Vec::new();
+
+    for proxy in origin {
+        let Some(old) = proxy.get("name").and_then(Value::as_str) else {
+            proxies.push(proxy.clone());
+            continue;
+        };
+        let keep = (include.is_empty() || include.iter().any(|re| re.is_match(old)))
+            && !exclude.iter().any(|re| re.is_match(old));
+        if !keep {
+            dropped.insert(old.to_string());
+            continue;
+        }
+
+        let mut name = old.to_string();
+        for (regex, replace) in renames.iter() {
+            name = regex.replace_all(&name, replace.as_str()).into_owned();
+        }
+        let region = region_of(&name);
+        if let (true, Some(code)) = (transform.flag, region) {
+            if !has_flag(&name) {
+                name = format!("{} {name}", flag(code));
+            }
+        }
+        // the renamed proxies must not share a name
+        let base = name.clone();
+        let mut i = 2;
+        while taken.contains(&name) || (name != old && reserved.contains(name.as_str())) {
+            name = format!("{base} {i}");
+            i += 1;
+        }
+        taken.insert(name.clone());
+
+        if let Some(code) = region {
+            match regions.iter_mut().find(|(c, _)| *c == code) {
+                Some((_, names)) => names.push(name.clone()),
+                None => regions.push((code, vec![name.clone()])),
+            }
+        }
+        let mut proxy = proxy.clone();
+        if name != old {
+            renamed.insert(old.to_string(), name.clone());
+            if let Some(map) = proxy.as_mapping_mut() {
+                map.insert("name".into(), name.into());
+            }
+        }
+        proxies.push(proxy);
+    }
+    config.insert("proxies".into(), proxies.into());
+
+    if let Some(Value::Sequence(groups)) = config.get_mut("proxy-groups") {
+        for group in groups.iter_mut().filter_map(Value::as_mapping_mut) {
+            let provided = group.contains_key("use") || group.contains_key("include-all");
+            let Some(Value::Sequence(names)) = group.get_mut("proxies") else {
+                continue;
+            };
+            let had_proxies = !names.is_empty();
+            names.retain(|name| !name.as_str().is_some_and(|name| dropped.contains(name)));
+            for name in names.iter_mut() {
+                if let Some(new) = name.as_str().and_then(|name| renamed.get(name)) {
+                    *name = new.clone().into();
+                }
+            }
+            // a group without any proxy is rejected by the core
+            if had_proxies && names.is_empty() && !provided {
+                names.push("DIRECT".into());
+            }
+        }
+    }
+
+    if let Some(Value::Sequence(rules)) = config.get_mut("rules") {
+        rules.retain_mut(|rule| {
+            let Some(text) = rule.as_str() else {
+                return true;
+            };
+            let Some((index, target)) = rule_target(text) else {
+                return true;
+            };
+            if dropped.contains(target) {
+                warnings.push(format!("rule `{text}` targets a dropped proxy, removed"));
+                return false;
+            }
+            if let Some(new) = renamed.get(target) {
+                let mut parts: Vec<&str> = text.split(',').collect();
+                parts[index] = new;
+                *rule = parts.join(",").into();
+            }
+            true
+        });
+    }
+
+    if let Some(region_groups) = transform.region_groups {
+        let mut groups = match config.get("proxy-groups") {
+            Some(Value::Sequence(groups)) => groups.clone(),
+            _ => Sequence::new(),
+        };
+        let group_names: HashSet<String> = groups
+            .iter()
+            .filter_map(|group| group.get("name")?.as_str().map(String::from))
+            .collect();
+
+        for (code, names) in regions {
+            if !region_groups.regions.is_empty()
+                && !region_groups
+                    .regions
+                    .iter()
+                    .any(|r| r.eq_ignore_ascii_case(code))
+            {
+                continue;
+            }
+            let name = match transform.flag {
+                true => format!("{} {code}", flag(code)),
+                false => code.to_string(),
+            };
+            if group_names.contains(&name) || taken.contains(&name) {
+                warnings.push(format!("region group `{name}` already exists, skipped"));
+                continue;
+            }
+            let mut group = Mapping::new();
+            group.insert("name".into(), name.into());
+            group.insert("type".into(), region_groups.gtype.clone().into());
+            group.insert("proxies".into(), names.into());
+            group.insert("url".into(), region_groups.url.clone().into());
+            group.insert("interval".into(), region_groups.interval.into());
+            groups.push(group.into());
+        }
+        config.insert("proxy-groups".into(), groups.into());
+    }
+
+    (config, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+proxies:
+  - {name: "[HK] 01", type: ss}
+  - {name: "[HK] 02", type: ss}
+  - {name: "[JP] Tokyo", type: ss}
+  - {name: "Expire 2030-01-01", type: ss}
+  - {name: "US 01", type: ss}
+proxy-groups:
+  - {name: Proxy, type: select, proxies: ["[HK] 01", "[JP] Tokyo", "Expire 2030-01-01"]}
+  - {name: Info, type: select, proxies: ["Expire 2030-01-01"]}
+rules:
+  - "DOMAIN,example.com,[JP] Tokyo"
+  - "MATCH,Proxy"
+"#;
+
+    #[test]
+    fn test_transform() {
+        let transform: ProxyTransform = serde_yaml::from_str(
+            r#"
+exclude: ["(?i)expire"]
+rename:
+  - {pattern: '^\[(\w+)\] (.+)$', replace: "$1 $2"}
+flag: true
+region-groups: {type: fallback, regions: [hk, jp]}
+"#,
+        )
+        .unwrap();
+        let config: Mapping = serde_yaml::from_str(CONFIG).unwrap();
+        let (config, warnings) = use_transform(transform, config);
+        assert!(warnings.is_empty(), "{warnings:?}");
+
+        let names: Vec<&str> = config["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["🇭🇰 HK 01", "🇭🇰 HK 02", "🇯🇵 JP Tokyo", "🇺🇸 US 01"]);
+
+        let groups = config["proxy-groups"].as_sequence().unwrap();
+        assert_eq!(
+            groups[0]["proxies"],
+            serde_yaml::from_str::<Value>(r#"["🇭🇰 HK 01", "🇯🇵 JP Tokyo"]"#).unwrap()
+        );
+        assert_eq!(groups[1]["proxies"][0], "DIRECT");
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[2]["name"], "🇭🇰 HK");
+        assert_eq!(groups[2]["type"], "fallback");
+        assert_eq!(groups[2]["proxies"].as_sequence().unwrap().len(), 2);
+        assert_eq!(groups[3]["name"], "🇯🇵 JP");
+        assert_eq!(config["rules"][0], "DOMAIN,example.com,🇯🇵 JP Tokyo");
+    }
+
+    #[test]
+    fn test_include_and_invalid_regex() {
+        let transform = ProxyTransform {
+            include: vec!["HK".into(), "(".into()],
+            ..Default::default()
+        };
+        let config: Mapping = serde_yaml::from_str(CONFIG).unwrap();
+        let (config, warnings) = use_transform(transform, config);
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert_eq!(config["proxies"].as_sequence().unwrap().len(), 2);
+        assert_eq!(config["rules"].as_sequence().unwrap().len(), 1);
+        assert_eq!(region_of("Hong Kong 01"), Some("HK"));
+        assert_eq!(region_of("HKT"), None);
+        assert_eq!(region_of("东京 01"), Some("JP"));
+    }
+
+    #[test]
+    fn test_rename_keeps_names_unique() {
+        let transform: ProxyTransform = serde_yaml::from_str(
+            r#"
+rename:
+  - {pattern: '^\[HK\] 01$', replace: "Proxy"}
+  - {pattern: '^\[HK\] 02$', replace: "US 01"}
+"#,
+        )
+        .unwrap();
+        let config: Mapping = serde_yaml::from_str(CONFIG).unwrap();
+        let (config, _) = use_transform(transform, config);
+
+        let names: Vec<&str> = config["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "Proxy 2",
+                "US 01 2",
+                "[JP] Tokyo",
+                "Expire 2030-01-01",
+                "US 01"
+            ]
+        );
+        assert_eq!(config["rules"][1], "MATCH,Proxy");
+    }
+}
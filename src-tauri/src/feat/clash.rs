@@ -5,7 +5,9 @@ use crate::{
     module::mihomo::MihomoManager,
     utils::resolve,
 };
+use serde::Serialize;
 use serde_yaml::{Mapping, Value};
+use std::net::IpAddr;
 use tauri::Manager;
 
 /// Restart the Clash core
@@ -111,3 +113,114 @@ pub async fn test_delay(url: String) -> anyhow::Result<u32> {
         }
     }
 }
+
+/// How a domain resolves through the dns of the core and through the system resolver
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DnsDiagnosis {
+    pub domain: String,
+    /// `A` and `AAAA` answers of the core
+    pub core: Vec<IpAddr>,
+    pub core_error: Option<String>,
+    pub system: Vec<IpAddr>,
+    pub system_error: Option<String>,
+    /// the core answered from its fake-ip pool, so the addresses cannot match
+    pub fake_ip: bool,
+    /// both resolved and share at least one address
+    pub consistent: bool,
+    pub only_core: Vec<IpAddr>,
+    pub only_system: Vec<IpAddr>,
+}
+
+/// Resolve `domain` through the core and through the system resolver and compare the answers
+pub async fn diagnose_dns(domain: String) -> anyhow::Result<DnsDiagnosis> {
+    let domain = domain.trim().trim_end_matches('.').to_string();
+    if domain.is_empty() {
+        anyhow::bail!("domain is empty");
+    }
+
+    let mut diagnosis = DnsDiagnosis {
+        domain: domain.clone(),
+        ..DnsDiagnosis::default()
+    };
+
+    let mihomo = MihomoManager::global();
+    for record_type in ["A", "AAAA"] {
+        match mihomo.query_dns(&domain, Some(record_type)).await {
+            Ok(answer) => diagnosis.core.extend(answer.addresses()),
+            Err(err) => diagnosis.core_error = Some(err.to_string()),
+        }
+    }
+    // 有一种记录查到结果即可，另一种查询失败不算错误
+    if !diagnosis.core.is_empty() {
+        diagnosis.core_error = None;
+    }
+
+    // 系统解析器在TUN模式下同样会被内核接管
+    match tokio::net::lookup_host((domain.as_str(), 0)).await {
+        Ok(addrs) => {
+            for addr in addrs {
+                if !diagnosis.system.contains(&addr.ip()) {
+                    diagnosis.system.push(addr.ip());
+                }
+            }
+        }
+        Err(err) => diagnosis.system_error = Some(err.to_string()),
+    }
+
+    let fake_ip_range = fake_ip_range();
+    diagnosis.fake_ip = fake_ip_range
+        .as_deref()
+        .is_some_and(|range| diagnosis.core.iter().any(|ip| in_cidr(ip, range)));
+    diagnosis.only_core = diagnosis
+        .core
+        .iter()
+        .filter(|ip| !diagnosis.system.contains(ip))
+        .cloned()
+        .collect();
+    diagnosis.only_system = diagnosis
+        .system
+        .iter()
+        .filter(|ip| !diagnosis.core.contains(ip))
+        .cloned()
+        .collect();
+    diagnosis.consistent = diagnosis
+        .core
+        .iter()
+        .any(|ip| diagnosis.system.contains(ip));
+
+    Ok(diagnosis)
+}
+
+/// `dns.fake-ip-range` of the running config when the core hands out fake ips
+fn fake_ip_range() -> Option<String> {
+    let runtime = Config::runtime();
+    let runtime = runtime.latest();
+    let dns = runtime.config.as_ref()?.get("dns")?.as_mapping()?;
+    let enable = dns.get("enable").and_then(Value::as_bool).unwrap_or(false);
+    let mode = dns.get("enhanced-mode").and_then(Value::as_str);
+    if !enable || mode != Some("fake-ip") {
+        return None;
+    }
+    let range = dns.get("fake-ip-range").and_then(Value::as_str);
+    Some(range.unwrap_or("198.18.0.1/16").to_string())
+}
+
+fn in_cidr(ip: &IpAddr, cidr: &str) -> bool {
+    let Some((network, prefix)) = cidr.split_once('/') else {
+        return false;
+    };
+    let (Ok(network), Ok(prefix)) = (network.parse::<IpAddr>(), prefix.parse::<u32>()) else {
+        return false;
+    };
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) if prefix <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(*ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) if prefix <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(*ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
@@ -1,9 +1,14 @@
 use crate::{
     cmd,
-    config::{Config, PrfItem, PrfOption},
+    config::{Config, FetchPath, PrfFetch, PrfItem, PrfOption},
     core::{handle, CoreManager, *},
 };
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
+use std::time::Duration;
+use sysproxy::Sysproxy;
+
+/// 订阅下载失败后换下一种方式前的等待时间，每次翻倍
+const RETRY_BACKOFF: Duration = Duration::from_secs(2);
 
 /// Toggle proxy profile
 pub fn toggle_proxy_profile(profile_index: String) {
@@ -43,24 +48,54 @@ pub async fn update_profile(uid: String, option: Option<PrfOption>) -> Result<()
                 uid,
                 item.url.clone().unwrap()
             );
-            Some((item.url.clone().unwrap(), item.option.clone()))
+            Some((
+                item.url.clone().unwrap(),
+                item.option.clone(),
+                item.etag.clone(),
+                item.last_modified.clone(),
+            ))
         }
     };
 
     let should_update = match url_opt {
-        Some((url, opt)) => {
+        Some((url, opt, etag, last_modified)) => {
             println!("[订阅更新] 开始下载新的订阅内容");
             let merged_opt = PrfOption::merge(opt, option);
-            let item = PrfItem::from_url(&url, None, None, merged_opt).await?;
+            let (item, fetch) = fetch_profile(&url, merged_opt, etag, last_modified).await;
 
-            println!("[订阅更新] 更新订阅配置");
             let profiles = Config::profiles();
             let mut profiles = profiles.latest();
-            profiles.update_item(uid.clone(), item)?;
+            // 无论成功与否都记录下载方式和每次失败的原因
+            profiles.patch_item(
+                uid.clone(),
+                PrfItem {
+                    fetch: Some(fetch),
+                    ..PrfItem::default()
+                },
+            )?;
+            match item? {
+                Some(item) => {
+                    println!("[订阅更新] 更新订阅配置");
+                    profiles.update_item(uid.clone(), item)?;
 
-            let is_current = Some(uid.clone()) == profiles.get_current();
-            println!("[订阅更新] 是否为当前使用的订阅: {}", is_current);
-            is_current
+                    let is_current = Some(uid.clone()) == profiles.get_current()
+                        || profiles.current_sources().contains(&uid);
+                    println!("[订阅更新] 是否为当前使用的订阅: {}", is_current);
+                    is_current
+                }
+                // 订阅内容没有变化，只记录检查时间，不重写文件也不重载内核
+                None => {
+                    println!("[订阅更新] {} 订阅内容未变化", uid);
+                    profiles.patch_item(
+                        uid.clone(),
+                        PrfItem {
+                            updated: Some(chrono::Local::now().timestamp() as usize),
+                            ..PrfItem::default()
+                        },
+                    )?;
+                    false
+                }
+            }
         }
         None => true,
     };
@@ -82,3 +117,70 @@ pub async fn update_profile(uid: String, option: Option<PrfOption>) -> Result<()
 
     Ok(())
 }
+
+/// 依次直连、通过自身代理、通过系统代理下载订阅，连接失败或超时才退避后换下一种方式
+/// `self_proxy` 或 `with_proxy` 指定的方式最先尝试
+async fn fetch_profile(
+    url: &str,
+    option: Option<PrfOption>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+) -> (Result<Option<PrfItem>>, PrfFetch) {
+    let preferred = FetchPath::from_option(option.as_ref());
+    let system_proxy = Sysproxy::get_system_proxy().is_ok_and(|p| p.enable);
+    let paths = [
+        FetchPath::Direct,
+        FetchPath::SelfProxy,
+        FetchPath::SystemProxy,
+    ]
+    .into_iter()
+    .filter(|path| *path != preferred)
+    // 没有开启系统代理时这一步和直连相同
+    .filter(|path| *path != FetchPath::SystemProxy || system_proxy);
+
+    let mut fetch = PrfFetch::default();
+    let mut backoff = RETRY_BACKOFF;
+    for (i, path) in std::iter::once(preferred).chain(paths).enumerate() {
+        if i > 0 {
+            println!("[订阅更新] {}秒后通过 {} 重试", backoff.as_secs(), path);
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+        match PrfItem::from_url_if_modified(
+            url,
+            option.clone(),
+            path,
+            etag.clone(),
+            last_modified.clone(),
+        )
+        .await
+        {
+            Ok(item) => {
+                fetch.path = Some(path);
+                return (Ok(item), fetch);
+            }
+            Err(err) => {
+                log::warn!(target: "app", "failed to fetch {url} ({path}): {err:#}");
+                fetch.errors.push(format!("{path}: {err:#}"));
+                // 服务器已经响应，换一种方式下载也是同样的内容
+                if !is_transport_error(&err) {
+                    return (Err(err), fetch);
+                }
+            }
+        }
+    }
+
+    let err = anyhow!(
+        "failed to fetch the remote profile, {}",
+        fetch.errors.join("; ")
+    );
+    (Err(err), fetch)
+}
+
+/// 连接失败、超时或 TLS 握手失败，换一种下载方式可能成功
+/// reqwest 把 TLS 握手失败也算作连接错误
+fn is_transport_error(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|err| err.is_connect() || err.is_timeout())
+}
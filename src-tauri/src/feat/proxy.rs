@@ -1,8 +1,12 @@
 use crate::{
     config::{Config, IVerge},
     core::handle,
+    log_err,
+    module::mihomo::MihomoManager,
 };
-use std::env;
+use anyhow::Result;
+use futures::StreamExt;
+use std::{env, time::Duration};
 use tauri_plugin_clipboard_manager::ClipboardExt;
 
 /// Toggle system proxy on/off
@@ -47,6 +51,112 @@ pub fn toggle_tun_mode(not_save_file: Option<bool>) {
     });
 }
 
+/// Switch the selected node of a proxy group
+/// Closes the connections still going through the previous node when `auto_close_connection` is on
+pub async fn select_proxy(group: String, proxy: String) -> Result<()> {
+    let mihomo = MihomoManager::global();
+    let previous = mihomo.refresh_proxies().await.ok().and_then(|m| {
+        m.get_proxies()
+            .proxies
+            .get(&group)
+            .and_then(|g| g.now.clone())
+    });
+
+    mihomo.select_proxy(&group, &proxy).await?;
+    log_err!(Config::profiles().data().select_current(&group, &proxy));
+
+    let auto_close = { Config::verge().latest().auto_close_connection };
+    if !auto_close.unwrap_or(true) {
+        return Ok(());
+    }
+
+    if let Some(previous) = previous.filter(|previous| *previous != proxy) {
+        match mihomo.close_connections_by_chain(&previous).await {
+            Ok(count) => {
+                log::info!(target: "app", "closed {count} connections through `{previous}`")
+            }
+            Err(err) => log::warn!(target: "app", "failed to close connections: {err}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Put the nodes saved in the current profile back into their selector groups
+/// The core starts every group on its first node, so this runs after each (re)load
+/// Waits a few seconds for the controller when the core is still starting
+pub async fn restore_selected() -> Result<()> {
+    let selected = { Config::profiles().latest().current_selected() };
+    if selected.is_empty() {
+        return Ok(());
+    }
+
+    let mihomo = MihomoManager::global();
+    let mut retry = 0;
+    let proxies = loop {
+        match mihomo.refresh_proxies().await {
+            Ok(mihomo) => break mihomo.get_proxies().proxies,
+            Err(err) if err.is_unreachable() && retry < 5 => {
+                retry += 1;
+                tokio::time::sleep(Duration::from_millis(100 << retry)).await;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+
+    let mut restored = 0;
+    for each in selected {
+        let (Some(name), Some(now)) = (each.name, each.now) else {
+            continue;
+        };
+        let Some(group) = proxies.get(&name) else {
+            continue;
+        };
+        // the nodes of a group may change with the subscription
+        let available = group.all.as_ref().is_some_and(|all| all.contains(&now));
+        if group.proxy_type != "Selector" || !available || group.now.as_ref() == Some(&now) {
+            continue;
+        }
+        match mihomo.select_proxy(&name, &now).await {
+            Ok(_) => {
+                restored += 1;
+                log::info!(target: "app", "restored `{now}` in `{name}`");
+            }
+            Err(err) => log::warn!(target: "app", "failed to restore `{name}`: {err}"),
+        }
+    }
+    // the reload already refreshed the frontend when nothing changed
+    if restored > 0 {
+        handle::Handle::refresh_clash();
+    }
+
+    Ok(())
+}
+
+/// Test the nodes of a group from the client side, `concurrency` at a time
+/// Every result is pushed to the frontend as soon as it arrives
+pub async fn test_proxies_delay(
+    group: String,
+    names: Vec<String>,
+    url: Option<String>,
+    timeout: i32,
+    concurrency: usize,
+) -> Result<()> {
+    let mut results =
+        Box::pin(MihomoManager::global().test_proxies_delay(names, url, timeout, concurrency));
+    while let Some((name, result)) = results.next().await {
+        let delay = match result {
+            Ok(delay) => Some(delay.delay),
+            Err(err) => {
+                log::debug!(target: "app", "delay test of `{name}` failed: {err}");
+                None
+            }
+        };
+        handle::Handle::proxy_delay(&group, &name, delay);
+    }
+    Ok(())
+}
+
 /// Copy proxy environment variables to clipboard
 pub fn copy_clash_env() {
     // 从环境变量获取IP地址，默认127.0.0.1
@@ -1,5 +1,6 @@
 mod cmd;
 mod config;
+mod convert;
 mod core;
 mod enhance;
 mod feat;
@@ -182,12 +183,20 @@ pub fn run() {
             cmd::change_clash_core,
             cmd::get_runtime_config,
             cmd::get_runtime_yaml,
+            cmd::export_runtime_config,
             cmd::get_runtime_exists,
             cmd::get_runtime_logs,
+            cmd::get_runtime_diagnostics,
+            cmd::get_enhance_trace,
             cmd::invoke_uwp_tool,
             cmd::copy_clash_env,
             cmd::get_proxies,
             cmd::get_providers_proxies,
+            cmd::select_proxy,
+            cmd::update_proxy_provider,
+            cmd::healthcheck_proxy_provider,
+            cmd::get_rule_providers,
+            cmd::update_rule_provider,
             cmd::save_dns_config,
             cmd::apply_dns_config,
             cmd::check_dns_config_exists,
@@ -213,6 +222,9 @@ pub fn run() {
             cmd::reorder_profile,
             cmd::update_profile,
             cmd::delete_profile,
+            cmd::get_profile_versions,
+            cmd::diff_profile_version,
+            cmd::restore_profile_version,
             cmd::read_profile_file,
             cmd::save_profile_file,
             // script validation
@@ -220,6 +232,15 @@ pub fn run() {
             cmd::validate_script_file,
             // clash api
             cmd::clash_api_get_proxy_delay,
+            cmd::clash_api_get_group_delay,
+            cmd::test_proxies_delay,
+            cmd::dns_query,
+            cmd::flush_fakeip,
+            cmd::diagnose_dns,
+            cmd::get_connections,
+            cmd::close_connection,
+            cmd::close_all_connections,
+            cmd::close_connections,
             // backup
             cmd::create_webdav_backup,
             cmd::save_webdav_config,
@@ -1,10 +1,10 @@
 use crate::config::Config;
-use mihomo_api;
+use mihomo_api::{self, Transport};
 use once_cell::sync::{Lazy, OnceCell};
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::sync::Mutex;
-use tauri::http::{HeaderMap, HeaderValue};
-#[cfg(target_os = "macos")]
-use tokio_tungstenite::tungstenite::http;
+use tauri::http::HeaderMap;
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Rate {
@@ -27,15 +27,25 @@ impl MihomoManager {
     pub fn global() -> mihomo_api::MihomoManager {
         let instance = MihomoManager::__global();
         let (current_server, headers) = MihomoManager::get_clash_client_info().unwrap();
+        let transport = MihomoManager::get_clash_transport();
+        // 走 socket 时 host 只用于 Host 头
+        let current_server = match transport {
+            Transport::Tcp => current_server,
+            _ => "http://localhost".into(),
+        };
 
-        let lock = instance.mihomo.lock().unwrap();
+        let mut lock = instance.mihomo.lock().unwrap();
         if let Some(mihomo) = lock.get() {
-            if mihomo.get_mihomo_server() == current_server {
+            if mihomo.get_mihomo_server() == current_server
+                && *mihomo.get_transport() == transport
+                && *mihomo.get_headers() == headers
+            {
                 return mihomo.clone();
             }
         }
 
-        lock.set(mihomo_api::MihomoManager::new(current_server, headers))
+        lock.take();
+        lock.set(mihomo_api::MihomoManager::new(current_server, headers).with_transport(transport))
             .ok();
         lock.get().unwrap().clone()
     }
@@ -54,17 +64,41 @@ impl MihomoManager {
 
         Some((server, headers))
     }
-    #[cfg(target_os = "macos")]
-    pub fn get_traffic_ws_url() -> (String, HeaderValue) {
-        let (url, headers) = MihomoManager::get_clash_client_info().unwrap();
-        let ws_url = url.replace("http://", "ws://") + "/traffic";
-        let auth = headers
-            .get("Authorization")
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-        let token = http::header::HeaderValue::from_str(&auth).unwrap();
-        (ws_url, token)
+
+    /// 运行时配置启用了 `external-controller-unix` (unix) 或
+    /// `external-controller-pipe` (windows) 时改走本地 socket
+    ///
+    /// 读取已应用的运行时配置，草稿里的新 socket 在内核重载前还不可用
+    pub fn get_clash_transport() -> Transport {
+        let runtime = Config::runtime();
+        let runtime = runtime.data();
+        let Some(config) = runtime.config.as_ref() else {
+            return Transport::Tcp;
+        };
+        let get = |key: &str| {
+            config
+                .get(key)
+                .and_then(|value| value.as_str())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        #[cfg(unix)]
+        if let Some(path) = get("external-controller-unix") {
+            // 相对路径由内核按 home 目录 (-d) 解析
+            let path = PathBuf::from(path);
+            if path.is_absolute() {
+                return Transport::Unix(path);
+            }
+            if let Ok(home) = crate::utils::dirs::app_home_dir() {
+                return Transport::Unix(home.join(path));
+            }
+        }
+        #[cfg(windows)]
+        if let Some(pipe) = get("external-controller-pipe") {
+            return Transport::Pipe(pipe);
+        }
+
+        Transport::Tcp
     }
 }
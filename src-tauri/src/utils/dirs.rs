@@ -77,6 +77,11 @@ pub fn app_profiles_dir() -> Result<PathBuf> {
     Ok(app_home_dir()?.join("profiles"))
 }
 
+/// fetched versions of the remote profiles
+pub fn app_history_dir() -> Result<PathBuf> {
+    Ok(app_profiles_dir()?.join("history"))
+}
+
 /// logs dir
 pub fn app_logs_dir() -> Result<PathBuf> {
     Ok(app_home_dir()?.join("logs"))
@@ -4,5 +4,6 @@ pub mod help;
 pub mod i18n;
 pub mod init;
 pub mod resolve;
+pub mod rule;
 pub mod server;
 pub mod tmpl;
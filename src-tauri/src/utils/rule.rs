@@ -0,0 +1,43 @@
+/// The index (split by `,`) and the policy a rule points at
+/// the policy follows the payload and comes before options like `no-resolve`
+pub fn rule_target(rule: &str) -> Option<(usize, &str)> {
+    let parts: Vec<&str> = rule.split(',').collect();
+    let index = match parts.first()?.trim() {
+        rtype if rtype.eq_ignore_ascii_case("MATCH") => 1,
+        // `AND,((DOMAIN,a.com),(NETWORK,UDP)),target`, the payload has commas
+        _ if parts.get(1)?.trim_start().starts_with('(') => {
+            parts
+                .iter()
+                .rposition(|part| part.trim_end().ends_with(')'))?
+                + 1
+        }
+        _ => 2,
+    };
+    let target = parts.get(index)?.trim();
+    (!target.is_empty()).then_some((index, target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_target() {
+        assert_eq!(rule_target("MATCH,Proxy"), Some((1, "Proxy")));
+        assert_eq!(
+            rule_target("IP-CIDR,1.1.1.1/32,G,no-resolve"),
+            Some((2, "G"))
+        );
+        assert_eq!(
+            rule_target("AND,((DOMAIN,a.com),(NETWORK,UDP)),G"),
+            Some((5, "G"))
+        );
+        assert_eq!(
+            rule_target("NOT,((DST-PORT,80)),G,no-resolve"),
+            Some((3, "G"))
+        );
+        assert_eq!(rule_target("MATCH"), None);
+        assert_eq!(rule_target("DOMAIN,a.com"), None);
+        assert_eq!(rule_target("AND,((DOMAIN,a.com))"), None);
+    }
+}
@@ -26,6 +26,8 @@ pub const ITEM_MERGE_EMPTY: &str = "# Profile Enhancement Merge Template for Err
 
 /// enhanced profile
 pub const ITEM_SCRIPT: &str = "// Define main function (script entry)
+// Helpers: utils.filterProxies, utils.createGroup, utils.insertRulesBefore, utils.yaml ...
+// Check utils.version before using helpers added later
 
 function main(config, profileName) {
   return config;
@@ -61,3 +63,23 @@ append: []
 
 delete: []
 ";
+
+/// enhanced profile
+pub const ITEM_TRANSFORM: &str = "# Profile Enhancement Proxy Transform Template for ErrorX
+
+# keep only the proxies whose name matches one of the regexes, all when empty
+include: []
+
+# drop the proxies whose name matches one of the regexes
+exclude: []
+
+# applied in order, `replace` may refer to the captures as $1 or ${name}
+# - {pattern: '^\\[(\\w+)\\] (.+)$', replace: '$1 $2'}
+rename: []
+
+# prefix the names with the flag of the detected region
+flag: false
+
+# generate a url-test or fallback group for every region that has proxies
+# region-groups: {type: url-test, regions: [HK, JP, US]}
+";
@@ -0,0 +1,87 @@
+use std::fmt;
+
+pub type Result<T, E = MihomoError> = std::result::Result<T, E>;
+
+/// Errors of the controller api
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MihomoError {
+    /// nothing is listening on the controller, the core is not up (yet)
+    ConnectionRefused(String),
+    /// `401`, the `secret` does not match the one of the running core
+    Unauthorized,
+    /// `404`, unknown proxy, group, provider or connection
+    NotFound(String),
+    /// `400`, the core rejected the request, e.g. an invalid config on `PUT /configs`
+    BadRequest(String),
+    /// any other non-2xx status, with the `message` of the core
+    Status {
+        code: u16,
+        message: String,
+    },
+    /// the body is not the json that was expected
+    InvalidBody(String),
+    /// no answer within the timeout of the endpoint
+    Timeout,
+    Other(String),
+}
+
+impl MihomoError {
+    /// the core cannot be reached, retrying later may help
+    pub fn is_unreachable(&self) -> bool {
+        matches!(
+            self,
+            MihomoError::ConnectionRefused(_) | MihomoError::Timeout
+        )
+    }
+
+    pub(crate) fn from_status(code: u16, body: &str) -> Self {
+        // mihomo answers errors with `{"message": "..."}`
+        let message = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|value| value["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| body.trim().to_string());
+        match code {
+            400 => MihomoError::BadRequest(message),
+            401 => MihomoError::Unauthorized,
+            404 => MihomoError::NotFound(message),
+            _ => MihomoError::Status { code, message },
+        }
+    }
+}
+
+impl fmt::Display for MihomoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MihomoError::ConnectionRefused(err) => write!(f, "core is not reachable: {err}"),
+            MihomoError::Unauthorized => write!(f, "unauthorized, check the controller secret"),
+            MihomoError::NotFound(message) => write!(f, "not found: {message}"),
+            MihomoError::BadRequest(message) => write!(f, "rejected by core: {message}"),
+            MihomoError::Status { code, message } => write!(f, "status {code}: {message}"),
+            MihomoError::InvalidBody(err) => write!(f, "invalid response body: {err}"),
+            MihomoError::Timeout => write!(f, "request timed out"),
+            MihomoError::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for MihomoError {}
+
+impl From<reqwest::Error> for MihomoError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            MihomoError::Timeout
+        } else if err.is_connect() {
+            MihomoError::ConnectionRefused(err.to_string())
+        } else if err.is_decode() {
+            MihomoError::InvalidBody(err.to_string())
+        } else {
+            MihomoError::Other(err.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for MihomoError {
+    fn from(err: serde_json::Error) -> Self {
+        MihomoError::InvalidBody(err.to_string())
+    }
+}
@@ -1,30 +1,57 @@
-use reqwest::{Method, header::HeaderMap};
-use serde_json::json;
+use futures::{Stream, StreamExt};
+use reqwest::{Method, Url, header::HeaderMap};
 use std::{
+    collections::HashMap,
     sync::{Arc, Mutex},
     time::Duration,
 };
+pub mod error;
+pub use error::{MihomoError, Result};
 pub mod model;
-pub use model::{MihomoData, MihomoManager};
+pub use model::{
+    Connection, ConnectionFilter, ConnectionMetadata, Connections, DelayHistory, DnsQuery,
+    DnsQuestion, DnsRecord, MihomoData, MihomoManager, Proxies, Proxy, ProxyDelay, ProxyProvider,
+    ProxyProviders, RuleProvider, RuleProviders, SubscriptionInfo, Timeouts, Transport,
+};
+pub mod stream;
+pub use stream::{Backoff, LogEntry, LogLevel, Memory, Traffic};
+
+/// used by delay tests when no url is given
+pub const DEFAULT_TEST_URL: &str = "http://cp.cloudflare.com/generate_204";
 
 impl MihomoManager {
     pub fn new(mihomo_server: String, headers: HeaderMap) -> Self {
+        let transport = Transport::Tcp;
         Self {
             mihomo_server,
             data: Arc::new(Mutex::new(MihomoData {
-                proxies: serde_json::Value::Null,
-                providers_proxies: serde_json::Value::Null,
+                proxies: Proxies::default(),
+                providers_proxies: ProxyProviders::default(),
             })),
+            client: Self::build_client(&headers, &transport),
             headers,
+            transport,
+            timeouts: Timeouts::default(),
         }
     }
 
-    fn update_proxies(&self, proxies: serde_json::Value) {
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.client = Self::build_client(&self.headers, &transport);
+        self.transport = transport;
+        self
+    }
+
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    fn update_proxies(&self, proxies: Proxies) {
         let mut data = self.data.lock().unwrap();
         data.proxies = proxies;
     }
 
-    fn update_providers_proxies(&self, providers_proxies: serde_json::Value) {
+    fn update_providers_proxies(&self, providers_proxies: ProxyProviders) {
         let mut data = self.data.lock().unwrap();
         data.providers_proxies = providers_proxies;
     }
@@ -33,91 +60,132 @@ impl MihomoManager {
         self.mihomo_server.clone()
     }
 
-    pub fn get_proxies(&self) -> serde_json::Value {
+    pub fn get_transport(&self) -> &Transport {
+        &self.transport
+    }
+
+    pub fn get_headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn get_proxies(&self) -> Proxies {
         let data = self.data.lock().unwrap();
         data.proxies.clone()
     }
 
-    pub fn get_providers_proxies(&self) -> serde_json::Value {
+    pub fn get_providers_proxies(&self) -> ProxyProviders {
         let data = self.data.lock().unwrap();
         data.providers_proxies.clone()
     }
 
+    /// one client per manager, clones share its connection pool
+    fn build_client(headers: &HeaderMap, transport: &Transport) -> Result<reqwest::Client> {
+        let builder = reqwest::ClientBuilder::new()
+            .default_headers(headers.clone())
+            .no_proxy();
+        let builder = match transport {
+            Transport::Tcp => builder,
+            #[cfg(unix)]
+            Transport::Unix(path) => builder.unix_socket(path.as_path()),
+            #[cfg(windows)]
+            Transport::Pipe(pipe) => builder.windows_named_pipe(pipe.as_str()),
+            #[allow(unreachable_patterns)]
+            transport => {
+                return Err(MihomoError::Other(format!(
+                    "{transport:?} is not supported on this platform"
+                )));
+            }
+        };
+        Ok(builder.build()?)
+    }
+
+    /// `mihomo_server` joined with `segments`, each one percent-encoded,
+    /// so names with spaces, emoji or `/` stay a single path segment
+    fn endpoint(&self, segments: &[&str]) -> Result<Url> {
+        let mut url = Url::parse(&self.mihomo_server)
+            .map_err(|e| MihomoError::Other(format!("invalid controller url: {e}")))?;
+        url.path_segments_mut()
+            .map_err(|_| MihomoError::Other("invalid controller url".into()))?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    /// `null` for an empty body (`204 No Content`), the parsed json otherwise
     async fn send_request(
         &self,
         method: Method,
-        url: String,
+        url: Url,
         data: Option<serde_json::Value>,
-    ) -> Result<serde_json::Value, String> {
-        let client_response = reqwest::ClientBuilder::new()
-            .default_headers(self.headers.clone())
-            .no_proxy()
-            .timeout(Duration::from_secs(60))
-            .build()
-            .map_err(|e| e.to_string())?
-            .request(method.clone(), &url)
-            .json(&data.unwrap_or(json!({})))
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
-
-        let response = match method {
-            Method::PATCH => {
-                let status = client_response.status();
-                if status.as_u16() == 204 {
-                    json!({"code": 204})
-                } else {
-                    client_response
-                        .json::<serde_json::Value>()
-                        .await
-                        .map_err(|e| e.to_string())?
-                }
-            }
-            Method::PUT => json!(client_response.text().await.map_err(|e| e.to_string())?),
-            _ => client_response
-                .json::<serde_json::Value>()
-                .await
-                .map_err(|e| e.to_string())?,
-        };
-        Ok(response)
+        timeout: Duration,
+    ) -> Result<serde_json::Value> {
+        let client = self.client.as_ref().map_err(Clone::clone)?;
+        let mut request = client.request(method, url).timeout(timeout);
+        if let Some(data) = data {
+            request = request.json(&data);
+        }
+        let response = request.send().await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(MihomoError::from_status(status.as_u16(), &body));
+        }
+        if body.trim().is_empty() {
+            return Ok(serde_json::Value::Null);
+        }
+        Ok(serde_json::from_str(&body)?)
     }
 
-    pub async fn refresh_proxies(&self) -> Result<&Self, String> {
-        let url = format!("{}/proxies", self.mihomo_server);
-        let proxies = self.send_request(Method::GET, url, None).await?;
+    pub async fn refresh_proxies(&self) -> Result<&Self> {
+        let url = self.endpoint(&["proxies"])?;
+        let response = self
+            .send_request(Method::GET, url, None, self.timeouts.query)
+            .await?;
+        let proxies = serde_json::from_value::<Proxies>(response)?;
         self.update_proxies(proxies);
         Ok(self)
     }
 
-    pub async fn refresh_providers_proxies(&self) -> Result<&Self, String> {
-        let url = format!("{}/providers/proxies", self.mihomo_server);
-        let providers_proxies = self.send_request(Method::GET, url, None).await?;
+    pub async fn refresh_providers_proxies(&self) -> Result<&Self> {
+        let url = self.endpoint(&["providers", "proxies"])?;
+        let response = self
+            .send_request(Method::GET, url, None, self.timeouts.query)
+            .await?;
+        let providers_proxies = serde_json::from_value::<ProxyProviders>(response)?;
         self.update_providers_proxies(providers_proxies);
         Ok(self)
     }
 }
 
 impl MihomoManager {
-    pub async fn put_configs_force(&self, clash_config_path: &str) -> Result<(), String> {
-        let url = format!("{}/configs?force=true", self.mihomo_server);
+    /// `MihomoError::BadRequest` when the core rejects the config
+    pub async fn put_configs_force(&self, clash_config_path: &str) -> Result<()> {
+        let mut url = self.endpoint(&["configs"])?;
+        url.query_pairs_mut().append_pair("force", "true");
         let payload = serde_json::json!({
             "path": clash_config_path,
         });
-        let _response = self.send_request(Method::PUT, url, Some(payload)).await?;
+        self.send_request(Method::PUT, url, Some(payload), self.timeouts.reload)
+            .await?;
         Ok(())
     }
 
-    pub async fn patch_configs(&self, config: serde_json::Value) -> Result<(), String> {
-        let url = format!("{}/configs", self.mihomo_server);
-        let response = self.send_request(Method::PATCH, url, Some(config)).await?;
-        if response["code"] == 204 {
-            Ok(())
-        } else {
-            Err(response["message"]
-                .as_str()
-                .unwrap_or("unknown error")
-                .to_string())
-        }
+    pub async fn patch_configs(&self, config: serde_json::Value) -> Result<()> {
+        let url = self.endpoint(&["configs"])?;
+        self.send_request(Method::PATCH, url, Some(config), self.timeouts.update)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn select_proxy(&self, group: &str, name: &str) -> Result<()> {
+        let url = self.endpoint(&["proxies", group])?;
+        let payload = serde_json::json!({
+            "name": name,
+        });
+        self.send_request(Method::PUT, url, Some(payload), self.timeouts.update)
+            .await?;
+        Ok(())
     }
 
     pub async fn test_proxy_delay(
@@ -125,13 +193,195 @@ impl MihomoManager {
         name: &str,
         test_url: Option<String>,
         timeout: i32,
-    ) -> Result<serde_json::Value, String> {
-        let test_url = test_url.unwrap_or("http://cp.cloudflare.com/generate_204".to_string());
-        let url = format!(
-            "{}/proxies/{}/delay?url={}&timeout={}",
-            self.mihomo_server, name, test_url, timeout
-        );
-        let response = self.send_request(Method::GET, url, None).await?;
-        Ok(response)
+    ) -> Result<ProxyDelay> {
+        let mut url = self.endpoint(&["proxies", name, "delay"])?;
+        url.query_pairs_mut()
+            .append_pair("url", test_url.as_deref().unwrap_or(DEFAULT_TEST_URL))
+            .append_pair("timeout", &timeout.to_string());
+        let response = self
+            .send_request(Method::GET, url, None, self.delay_timeout(timeout))
+            .await?;
+        Ok(serde_json::from_value::<ProxyDelay>(response)?)
+    }
+
+    /// `GET /group/{name}/delay`, tests every node of the group in the core
+    /// and returns the delays by node name, failed nodes are left out
+    ///
+    /// `expected_status` narrows what counts as success, e.g. `204` or `200-299`
+    pub async fn test_group_delay(
+        &self,
+        group: &str,
+        test_url: Option<String>,
+        timeout: i32,
+        expected_status: Option<String>,
+    ) -> Result<HashMap<String, u32>> {
+        let mut url = self.endpoint(&["group", group, "delay"])?;
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("url", test_url.as_deref().unwrap_or(DEFAULT_TEST_URL))
+                .append_pair("timeout", &timeout.to_string());
+            if let Some(expected) = expected_status.as_deref() {
+                query.append_pair("expected", expected);
+            }
+        }
+        let response = self
+            .send_request(Method::GET, url, None, self.delay_timeout(timeout))
+            .await?;
+        Ok(serde_json::from_value::<HashMap<String, u32>>(response)?)
+    }
+
+    /// test `names` one by one from the client side, at most `concurrency` at a time
+    ///
+    /// Results are yielded in the order they finish.
+    pub fn test_proxies_delay(
+        &self,
+        names: Vec<String>,
+        test_url: Option<String>,
+        timeout: i32,
+        concurrency: usize,
+    ) -> impl Stream<Item = (String, Result<ProxyDelay>)> + Send + use<> {
+        let manager = self.clone();
+        futures::stream::iter(names)
+            .map(move |name| {
+                let manager = manager.clone();
+                let test_url = test_url.clone();
+                async move {
+                    let result = manager.test_proxy_delay(&name, test_url, timeout).await;
+                    (name, result)
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+    }
+
+    fn delay_timeout(&self, timeout: i32) -> Duration {
+        Duration::from_millis(timeout.max(0) as u64) + self.timeouts.delay_margin
+    }
+}
+
+impl MihomoManager {
+    /// download a proxy provider again, without reloading the config
+    pub async fn update_proxy_provider(&self, name: &str) -> Result<()> {
+        let url = self.endpoint(&["providers", "proxies", name])?;
+        self.send_request(Method::PUT, url, None, self.timeouts.reload)
+            .await?;
+        Ok(())
+    }
+
+    /// run the health check of a proxy provider, the results land in the proxies' history
+    pub async fn healthcheck_proxy_provider(&self, name: &str) -> Result<()> {
+        let url = self.endpoint(&["providers", "proxies", name, "healthcheck"])?;
+        self.send_request(Method::GET, url, None, self.timeouts.reload)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_rule_providers(&self) -> Result<RuleProviders> {
+        let url = self.endpoint(&["providers", "rules"])?;
+        let response = self
+            .send_request(Method::GET, url, None, self.timeouts.query)
+            .await?;
+        Ok(serde_json::from_value::<RuleProviders>(response)?)
+    }
+
+    /// download a rule provider again, without reloading the config
+    pub async fn update_rule_provider(&self, name: &str) -> Result<()> {
+        let url = self.endpoint(&["providers", "rules", name])?;
+        self.send_request(Method::PUT, url, None, self.timeouts.reload)
+            .await?;
+        Ok(())
+    }
+}
+
+impl MihomoManager {
+    /// `GET /dns/query`, resolve `name` with the dns of the core, `A` when no type is given
+    ///
+    /// `MihomoError::Status` with code `500` when the dns of the core is disabled
+    pub async fn query_dns(&self, name: &str, record_type: Option<&str>) -> Result<DnsQuery> {
+        let mut url = self.endpoint(&["dns", "query"])?;
+        url.query_pairs_mut()
+            .append_pair("name", name)
+            .append_pair("type", record_type.unwrap_or("A"));
+        let response = self
+            .send_request(Method::GET, url, None, self.timeouts.query)
+            .await?;
+        Ok(serde_json::from_value::<DnsQuery>(response)?)
+    }
+
+    /// drop every mapping of the fake-ip pool, so cached fake ips are handed out anew
+    pub async fn flush_fakeip(&self) -> Result<()> {
+        let url = self.endpoint(&["cache", "fakeip", "flush"])?;
+        self.send_request(Method::POST, url, None, self.timeouts.update)
+            .await?;
+        Ok(())
+    }
+}
+
+impl MihomoManager {
+    pub async fn get_connections(&self) -> Result<Connections> {
+        let url = self.endpoint(&["connections"])?;
+        let response = self
+            .send_request(Method::GET, url, None, self.timeouts.query)
+            .await?;
+        Ok(serde_json::from_value::<Connections>(response)?)
+    }
+
+    pub async fn close_connection(&self, id: &str) -> Result<()> {
+        let url = self.endpoint(&["connections", id])?;
+        self.send_request(Method::DELETE, url, None, self.timeouts.update)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn close_all_connections(&self) -> Result<()> {
+        let url = self.endpoint(&["connections"])?;
+        self.send_request(Method::DELETE, url, None, self.timeouts.update)
+            .await?;
+        Ok(())
+    }
+
+    /// close every connection matched by `filter`, returns how many were closed
+    /// a failed close does not stop the others, the failures are returned together afterwards
+    pub async fn close_connections(&self, filter: &ConnectionFilter) -> Result<usize> {
+        let connections = self.get_connections().await?;
+        let mut closed = 0;
+        let mut errors = vec![];
+        for conn in connections.connections.iter().filter(|c| filter.matches(c)) {
+            match self.close_connection(&conn.id).await {
+                Ok(()) => closed += 1,
+                // the connection ended on its own in the meantime
+                Err(MihomoError::NotFound(_)) => {}
+                Err(err) => errors.push(format!("{}: {err}", conn.id)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(MihomoError::Other(format!(
+                "failed to close {} of {} connections: {}",
+                errors.len(),
+                closed + errors.len(),
+                errors.join("; ")
+            )));
+        }
+        Ok(closed)
+    }
+
+    pub async fn close_connections_by_host(&self, host: &str) -> Result<usize> {
+        self.close_connections(&ConnectionFilter::Host(host.into()))
+            .await
+    }
+
+    pub async fn close_connections_by_rule(&self, rule: &str) -> Result<usize> {
+        self.close_connections(&ConnectionFilter::Rule(rule.into()))
+            .await
+    }
+
+    pub async fn close_connections_by_chain(&self, proxy: &str) -> Result<usize> {
+        self.close_connections(&ConnectionFilter::Chain(proxy.into()))
+            .await
+    }
+
+    pub async fn close_connections_by_process(&self, process: &str) -> Result<usize> {
+        self.close_connections(&ConnectionFilter::Process(process.into()))
+            .await
     }
 }
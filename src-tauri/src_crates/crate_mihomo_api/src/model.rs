@@ -1,9 +1,17 @@
+use crate::MihomoError;
 use reqwest::header::HeaderMap;
-use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 pub struct MihomoData {
-    pub(crate) proxies: serde_json::Value,
-    pub(crate) providers_proxies: serde_json::Value,
+    pub(crate) proxies: Proxies,
+    pub(crate) providers_proxies: ProxyProviders,
 }
 
 #[derive(Clone)]
@@ -11,6 +19,353 @@ pub struct MihomoManager {
     pub(crate) mihomo_server: String,
     pub(crate) data: Arc<Mutex<MihomoData>>,
     pub(crate) headers: HeaderMap,
+    pub(crate) transport: Transport,
+    /// built once from `headers` and `transport`
+    pub(crate) client: Result<reqwest::Client, MihomoError>,
+    pub(crate) timeouts: Timeouts,
+}
+
+/// Request timeouts, per kind of endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// reads, `GET /proxies`, `GET /connections` ...
+    pub query: Duration,
+    /// small writes, selecting a proxy, `PATCH /configs`, closing connections ...
+    pub update: Duration,
+    /// `PUT /configs` and provider updates or health checks,
+    /// which wait for downloads or delay tests in the core
+    pub reload: Duration,
+    /// added on top of the test timeout of a delay test
+    pub delay_margin: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            query: Duration::from_secs(5),
+            update: Duration::from_secs(5),
+            reload: Duration::from_secs(60),
+            delay_margin: Duration::from_secs(2),
+        }
+    }
+}
+
+/// How the external controller is reached
+///
+/// With a socket transport the host part of `mihomo_server` is only used for
+/// the `Host` header, e.g. `http://localhost`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Transport {
+    /// `external-controller`, `host:port` over TCP
+    #[default]
+    Tcp,
+    /// `external-controller-unix`, path of the socket, unix only
+    Unix(PathBuf),
+    /// `external-controller-pipe`, e.g. `\\.\pipe\mihomo`, windows only
+    Pipe(String),
+}
+
+/// Response of `GET /proxies`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Proxies {
+    pub proxies: HashMap<String, Proxy>,
+}
+
+/// A single proxy node or proxy group
+///
+/// Groups (`Selector`, `URLTest`, `Fallback`, ...) carry `now` and `all`,
+/// plain nodes leave them empty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Proxy {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub proxy_type: String,
+    #[serde(default)]
+    pub udp: bool,
+    #[serde(default)]
+    pub xudp: bool,
+    #[serde(default)]
+    pub tfo: bool,
+    #[serde(default)]
+    pub history: Vec<DelayHistory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub now: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub all: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixed: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_url: Option<String>,
+    /// fields not covered above, kept as they came from the core
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+impl Proxy {
+    /// whether this entry is a proxy group
+    pub fn is_group(&self) -> bool {
+        self.all.is_some()
+    }
+
+    /// the latest delay record, `0` means the last test failed
+    pub fn last_delay(&self) -> Option<u32> {
+        self.history.last().map(|h| h.delay)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DelayHistory {
+    pub time: String,
+    pub delay: u32,
+}
+
+/// Response of `GET /providers/proxies`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProxyProviders {
+    pub providers: HashMap<String, ProxyProvider>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyProvider {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    /// `HTTP` | `File` | `Inline` | `Compatible`
+    pub vehicle_type: String,
+    #[serde(default)]
+    pub proxies: Vec<Proxy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription_info: Option<SubscriptionInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// fields not covered above, kept as they came from the core
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+/// Response of `GET /providers/rules`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleProviders {
+    pub providers: HashMap<String, RuleProvider>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleProvider {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    /// `HTTP` | `File` | `Inline`
+    pub vehicle_type: String,
+    /// `Domain` | `IPCIDR` | `Classical`
+    #[serde(default)]
+    pub behavior: String,
+    /// `YamlRule` | `TextRule` | `MrsRule`
+    #[serde(default)]
+    pub format: String,
+    #[serde(default)]
+    pub rule_count: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// fields not covered above, kept as they came from the core
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+/// mihomo serializes this struct without json tags, hence the capitalized keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SubscriptionInfo {
+    #[serde(default)]
+    pub upload: u64,
+    #[serde(default)]
+    pub download: u64,
+    #[serde(default)]
+    pub total: u64,
+    #[serde(default)]
+    pub expire: u64,
+}
+
+/// Response of `GET /proxies/{name}/delay`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyDelay {
+    pub delay: u32,
+}
+
+/// Response of `GET /dns/query`, the dns message of the core
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DnsQuery {
+    /// rcode, `0` is `NOERROR`, `3` is `NXDOMAIN`
+    pub status: u16,
+    #[serde(rename = "TC", default)]
+    pub truncated: bool,
+    #[serde(rename = "RD", default)]
+    pub recursion_desired: bool,
+    #[serde(rename = "RA", default)]
+    pub recursion_available: bool,
+    #[serde(rename = "AD", default)]
+    pub authenticated_data: bool,
+    #[serde(rename = "CD", default)]
+    pub checking_disabled: bool,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    pub question: Vec<DnsQuestion>,
+    /// the sections below are left out by the core when empty
+    #[serde(default)]
+    pub answer: Vec<DnsRecord>,
+    #[serde(default)]
+    pub authority: Vec<DnsRecord>,
+    #[serde(default)]
+    pub additional: Vec<DnsRecord>,
+}
+
+impl DnsQuery {
+    /// addresses of the `A` and `AAAA` records in the answer
+    pub fn addresses(&self) -> Vec<IpAddr> {
+        self.answer
+            .iter()
+            .filter(|record| matches!(record.record_type, DNS_TYPE_A | DNS_TYPE_AAAA))
+            .filter_map(|record| record.data.trim().parse().ok())
+            .collect()
+    }
+}
+
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_AAAA: u16 = 28;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DnsQuestion {
+    pub name: String,
+    pub qtype: u16,
+    #[serde(default)]
+    pub qclass: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsRecord {
+    pub name: String,
+    /// numeric record type, `1` for `A`, `28` for `AAAA`, `5` for `CNAME`
+    #[serde(rename = "type")]
+    pub record_type: u16,
+    #[serde(rename = "TTL", default)]
+    pub ttl: u32,
+    /// the record in presentation format without its header, e.g. `1.1.1.1`
+    #[serde(default)]
+    pub data: String,
+}
+
+/// Response of `GET /connections`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connections {
+    #[serde(default)]
+    pub download_total: u64,
+    #[serde(default)]
+    pub upload_total: u64,
+    /// the core reports `null` instead of `[]` when idle
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    pub connections: Vec<Connection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection {
+    pub id: String,
+    pub metadata: ConnectionMetadata,
+    #[serde(default)]
+    pub upload: u64,
+    #[serde(default)]
+    pub download: u64,
+    #[serde(default)]
+    pub start: String,
+    #[serde(default)]
+    pub chains: Vec<String>,
+    #[serde(default)]
+    pub rule: String,
+    #[serde(default)]
+    pub rule_payload: String,
+    /// fields not covered above, kept as they came from the core
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionMetadata {
+    #[serde(default)]
+    pub network: String,
+    #[serde(rename = "type", default)]
+    pub conn_type: String,
+    #[serde(rename = "sourceIP", default)]
+    pub source_ip: String,
+    #[serde(rename = "destinationIP", default)]
+    pub destination_ip: String,
+    #[serde(default)]
+    pub source_port: String,
+    #[serde(default)]
+    pub destination_port: String,
+    #[serde(default)]
+    pub host: String,
+    #[serde(default)]
+    pub sniff_host: String,
+    #[serde(default)]
+    pub process: String,
+    #[serde(default)]
+    pub process_path: String,
+    /// fields not covered above, kept as they came from the core
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+/// Selects the connections to be closed by `MihomoManager::close_connections`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "by", content = "value", rename_all = "lowercase")]
+pub enum ConnectionFilter {
+    /// requested host, sniffed host or destination ip
+    Host(String),
+    /// rule type (`DOMAIN-SUFFIX`, `MATCH` ...) or rule payload
+    Rule(String),
+    /// any proxy or group in the chain
+    Chain(String),
+    /// process name or full process path
+    Process(String),
+}
+
+impl ConnectionFilter {
+    pub fn matches(&self, conn: &Connection) -> bool {
+        let meta = &conn.metadata;
+        match self {
+            ConnectionFilter::Host(host) => {
+                meta.host.eq_ignore_ascii_case(host)
+                    || meta.sniff_host.eq_ignore_ascii_case(host)
+                    || meta.destination_ip == *host
+            }
+            ConnectionFilter::Rule(rule) => conn.rule == *rule || conn.rule_payload == *rule,
+            ConnectionFilter::Chain(proxy) => conn.chains.iter().any(|c| c == proxy),
+            ConnectionFilter::Process(process) => {
+                meta.process == *process || meta.process_path == *process
+            }
+        }
+    }
+}
+
+fn deserialize_null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
 }
 
 #[cfg(feature = "debug")]
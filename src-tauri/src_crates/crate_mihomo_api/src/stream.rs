@@ -0,0 +1,232 @@
+use crate::{Connections, MihomoManager, Transport};
+use futures::{Stream, StreamExt, stream};
+use reqwest::header::AUTHORIZATION;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{io, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_tungstenite::{
+    WebSocketStream, client_async,
+    tungstenite::{Message, client::IntoClientRequest, handshake::client::Request},
+};
+
+trait Io: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync> Io for T {}
+
+type WsStream = WebSocketStream<Box<dyn Io>>;
+
+/// Message of the `/traffic` websocket, bytes per second
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Traffic {
+    pub up: u64,
+    pub down: u64,
+}
+
+/// Message of the `/memory` websocket, in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Memory {
+    pub inuse: u64,
+    pub oslimit: u64,
+}
+
+/// Message of the `/logs` websocket
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    #[serde(rename = "type")]
+    pub level: String,
+    pub payload: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warning,
+    Error,
+    Silent,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warning => "warning",
+            LogLevel::Error => "error",
+            LogLevel::Silent => "silent",
+        }
+    }
+}
+
+/// Reconnect delay, doubled after every failed attempt or dropped socket
+/// and reset once a message arrives
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(30),
+        }
+    }
+}
+
+type ManagerSource = Arc<dyn Fn() -> MihomoManager + Send + Sync>;
+
+struct WsState {
+    /// asked again on every reconnect, the port, secret or transport may have changed
+    manager: ManagerSource,
+    path: String,
+    socket: Option<WsStream>,
+    backoff: Backoff,
+    delay: Duration,
+}
+
+impl WsState {
+    async fn connect(&mut self) {
+        let manager = (self.manager)();
+        let url = format!(
+            "{}{}",
+            manager.mihomo_server.replacen("http", "ws", 1),
+            self.path
+        );
+        let request = url.as_str().into_client_request().map(|mut request| {
+            if let Some(auth) = manager.headers.get(AUTHORIZATION).cloned() {
+                request.headers_mut().insert(AUTHORIZATION, auth);
+            }
+            request
+        });
+        let result = match request {
+            Ok(request) => match Self::open(&manager.transport, &request).await {
+                Ok(io) => client_async(request, io).await.ok(),
+                Err(_) => None,
+            },
+            Err(_) => None,
+        };
+        match result {
+            // the delay is only reset by a message, a server that closes right away still backs off
+            Some((socket, _)) => self.socket = Some(socket),
+            None => self.wait().await,
+        }
+    }
+
+    async fn wait(&mut self) {
+        tokio::time::sleep(self.delay).await;
+        self.delay = (self.delay * 2).min(self.backoff.max);
+    }
+
+    async fn open(transport: &Transport, request: &Request) -> io::Result<Box<dyn Io>> {
+        match transport {
+            Transport::Tcp => {
+                let authority = request.uri().authority().map(|a| a.as_str()).unwrap_or("");
+                let stream = match request.uri().port_u16() {
+                    Some(_) => TcpStream::connect(authority).await?,
+                    None => TcpStream::connect((authority, 80)).await?,
+                };
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            Transport::Unix(path) => Ok(Box::new(tokio::net::UnixStream::connect(path).await?)),
+            #[cfg(windows)]
+            Transport::Pipe(pipe) => Ok(Box::new(
+                tokio::net::windows::named_pipe::ClientOptions::new().open(pipe)?,
+            )),
+            #[allow(unreachable_patterns)]
+            transport => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{transport:?} is not supported on this platform"),
+            )),
+        }
+    }
+}
+
+impl MihomoManager {
+    /// `/traffic`, one message per second
+    pub fn traffic_stream(&self) -> impl Stream<Item = Traffic> + Send + use<> {
+        self.ws_stream("/traffic", Backoff::default())
+    }
+
+    /// `/memory`, one message per second
+    pub fn memory_stream(&self) -> impl Stream<Item = Memory> + Send + use<> {
+        self.ws_stream("/memory", Backoff::default())
+    }
+
+    /// `/logs?level=`, only entries at or above `level`
+    pub fn logs_stream(&self, level: LogLevel) -> impl Stream<Item = LogEntry> + Send + use<> {
+        self.ws_stream(
+            &format!("/logs?level={}", level.as_str()),
+            Backoff::default(),
+        )
+    }
+
+    /// `/connections`, a full snapshot every second
+    pub fn connections_stream(&self) -> impl Stream<Item = Connections> + Send + use<> {
+        self.ws_stream("/connections", Backoff::default())
+    }
+
+    /// Subscribe to a websocket endpoint of the controller
+    ///
+    /// The stream never ends: when the socket closes or the core is not up yet
+    /// it reconnects with `backoff`. Messages that fail to parse are skipped.
+    pub fn ws_stream<T>(
+        &self,
+        path: &str,
+        backoff: Backoff,
+    ) -> impl Stream<Item = T> + Send + use<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let manager = self.clone();
+        Self::ws_stream_from(move || manager.clone(), path, backoff)
+    }
+
+    /// Like `ws_stream`, but `manager` is asked for the controller on every reconnect,
+    /// so a stream that lives as long as the app follows port, secret and transport changes
+    pub fn ws_stream_from<T, F>(
+        manager: F,
+        path: &str,
+        backoff: Backoff,
+    ) -> impl Stream<Item = T> + Send + use<T, F>
+    where
+        T: DeserializeOwned + Send + 'static,
+        F: Fn() -> MihomoManager + Send + Sync + 'static,
+    {
+        let state = WsState {
+            manager: Arc::new(manager),
+            path: path.into(),
+            socket: None,
+            backoff,
+            delay: backoff.initial,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            loop {
+                let Some(socket) = state.socket.as_mut() else {
+                    state.connect().await;
+                    continue;
+                };
+                match socket.next().await {
+                    Some(Ok(Message::Text(text))) => {
+                        state.delay = state.backoff.initial;
+                        if let Ok(item) = serde_json::from_str::<T>(text.as_str()) {
+                            return Some((item, state));
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                        state.socket = None;
+                        state.wait().await;
+                    }
+                    Some(Ok(_)) => {}
+                }
+            }
+        })
+    }
+}
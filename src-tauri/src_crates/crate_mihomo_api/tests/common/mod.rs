@@ -0,0 +1,361 @@
+//! In-process stand-in for the mihomo external controller
+//!
+//! Implements the endpoints `mihomo_api` talks to with a small fixed state,
+//! and records every request so tests can assert on what was sent.
+
+use futures::{SinkExt, StreamExt};
+use percent_encoding::percent_decode_str;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use serde_json::{Value, json};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use warp::{
+    Filter, Reply,
+    http::{Method, StatusCode},
+    hyper::body::Bytes,
+    reply::Response,
+    ws::{Message, Ws},
+};
+
+pub const GROUP: &str = "Proxy";
+/// a node name that needs percent-encoding in every way
+pub const NODE: &str = "HK 01/🇭🇰";
+/// delay tests of this node time out in the core
+pub const SLOW_NODE: &str = "Slow";
+/// delay tests of this node never answer
+pub const HANGING_NODE: &str = "Hang";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recorded {
+    pub method: String,
+    /// percent-decoded path segments
+    pub segments: Vec<String>,
+    pub query: HashMap<String, String>,
+    pub authorization: Option<String>,
+    pub body: Option<Value>,
+}
+
+#[derive(Default)]
+struct State {
+    secret: Option<String>,
+    /// answer `GET /proxies` with html, like a captive portal would
+    html_proxies: bool,
+    now: String,
+    connections: Vec<Value>,
+    /// websocket upgrades of `/closing`, which hangs up right away
+    closing_upgrades: usize,
+    /// connections whose `DELETE` fails
+    failing: Vec<String>,
+    requests: Vec<Recorded>,
+}
+
+#[derive(Clone)]
+pub struct MockController {
+    pub addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockController {
+    /// serve on a random local port, requests must carry `Bearer {secret}` when given
+    pub async fn start(secret: Option<&str>) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            secret: secret.map(str::to_string),
+            html_proxies: false,
+            now: NODE.into(),
+            connections: vec![connection("c1", NODE), connection("c2", "DIRECT")],
+            closing_upgrades: 0,
+            failing: vec![],
+            requests: vec![],
+        }));
+
+        let ws_state = state.clone();
+        let traffic = warp::path("traffic")
+            .and(warp::path::end())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::ws())
+            .map(move |auth: Option<String>, ws: Ws| {
+                if !authorized(&ws_state, auth.as_deref()) {
+                    return unauthorized();
+                }
+                ws.on_upgrade(|mut socket| async move {
+                    for i in 1..=3u64 {
+                        let traffic = json!({"up": i, "down": i * 10}).to_string();
+                        if socket.send(Message::text(traffic)).await.is_err() {
+                            return;
+                        }
+                    }
+                    // keep the socket open until the client goes away
+                    while socket.next().await.is_some() {}
+                })
+                .into_response()
+            });
+
+        let closing_state = state.clone();
+        let closing = warp::path("closing")
+            .and(warp::path::end())
+            .and(warp::ws())
+            .map(move |ws: Ws| {
+                closing_state.lock().unwrap().closing_upgrades += 1;
+                ws.on_upgrade(|socket| async move {
+                    let _ = socket.close().await;
+                })
+            });
+
+        let http_state = state.clone();
+        let rest = warp::method()
+            .and(warp::path::full())
+            .and(warp::query::<HashMap<String, String>>())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::body::bytes())
+            .and_then(
+                move |method, path: warp::path::FullPath, query, auth, body| {
+                    let state = http_state.clone();
+                    async move {
+                        Ok::<_, Infallible>(
+                            handle(state, method, path.as_str(), query, auth, body).await,
+                        )
+                    }
+                },
+            );
+
+        let (addr, server) =
+            warp::serve(traffic.or(closing).or(rest)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        Self { addr, state }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// a manager pointing at this controller, with `secret` as bearer token
+    pub fn manager(&self, secret: Option<&str>) -> mihomo_api::MihomoManager {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+        if let Some(secret) = secret {
+            let bearer = HeaderValue::from_str(&format!("Bearer {secret}")).unwrap();
+            headers.insert(AUTHORIZATION, bearer);
+        }
+        mihomo_api::MihomoManager::new(self.url(), headers)
+    }
+
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn last_request(&self) -> Recorded {
+        self.requests().pop().expect("no request recorded")
+    }
+
+    pub fn serve_html(&self) {
+        self.state.lock().unwrap().html_proxies = true;
+    }
+
+    pub fn now(&self) -> String {
+        self.state.lock().unwrap().now.clone()
+    }
+
+    /// make closing the connection `id` fail
+    pub fn fail_close(&self, id: &str) {
+        self.state.lock().unwrap().failing.push(id.into());
+    }
+
+    pub fn closing_upgrades(&self) -> usize {
+        self.state.lock().unwrap().closing_upgrades
+    }
+
+    pub fn connection_ids(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state
+            .connections
+            .iter()
+            .map(|c| c["id"].as_str().unwrap().to_string())
+            .collect()
+    }
+}
+
+fn authorized(state: &Mutex<State>, auth: Option<&str>) -> bool {
+    match &state.lock().unwrap().secret {
+        Some(secret) => auth == Some(format!("Bearer {secret}").as_str()),
+        None => true,
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    method: Method,
+    path: &str,
+    query: HashMap<String, String>,
+    auth: Option<String>,
+    body: Bytes,
+) -> Response {
+    let segments: Vec<String> = path
+        .trim_start_matches('/')
+        .split('/')
+        .map(|s| percent_decode_str(s).decode_utf8_lossy().into_owned())
+        .collect();
+    let body: Option<Value> = serde_json::from_slice(&body).ok();
+    state.lock().unwrap().requests.push(Recorded {
+        method: method.to_string(),
+        segments: segments.clone(),
+        query: query.clone(),
+        authorization: auth.clone(),
+        body: body.clone(),
+    });
+
+    if !authorized(&state, auth.as_deref()) {
+        return unauthorized();
+    }
+
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    match (method, segments.as_slice()) {
+        (Method::GET, ["proxies"]) => {
+            let state = state.lock().unwrap();
+            if state.html_proxies {
+                return warp::reply::html("<html>login</html>").into_response();
+            }
+            ok(json!({"proxies": proxies(&state.now)}))
+        }
+        (Method::PUT, ["proxies", group]) => {
+            let name = body.as_ref().and_then(|b| b["name"].as_str()).unwrap_or("");
+            if *group != GROUP {
+                return error(StatusCode::NOT_FOUND, "resource not found");
+            }
+            if ![NODE, SLOW_NODE, HANGING_NODE, "DIRECT"].contains(&name) {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "Selector update error: proxy not exist",
+                );
+            }
+            state.lock().unwrap().now = name.into();
+            no_content()
+        }
+        (Method::GET, ["proxies", name, "delay"]) => {
+            let timeout = query
+                .get("timeout")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(5000);
+            match *name {
+                SLOW_NODE => {
+                    tokio::time::sleep(Duration::from_millis(timeout)).await;
+                    error(StatusCode::GATEWAY_TIMEOUT, "Timeout")
+                }
+                HANGING_NODE => {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    no_content()
+                }
+                NODE => ok(json!({"delay": 87})),
+                "DIRECT" => ok(json!({"delay": 3})),
+                _ => error(StatusCode::NOT_FOUND, "resource not found"),
+            }
+        }
+        (Method::GET, ["group", group, "delay"]) => {
+            if *group != GROUP {
+                return error(StatusCode::NOT_FOUND, "resource not found");
+            }
+            ok(json!({NODE: 87, "DIRECT": 3}))
+        }
+        (Method::GET, ["providers", "proxies"]) => ok(json!({"providers": {
+            "sub": {"name": "sub", "type": "Proxy", "vehicleType": "HTTP",
+                "proxies": [{"name": NODE, "type": "Shadowsocks", "history": []}]}
+        }})),
+        (Method::PUT, ["providers", "proxies", "sub"])
+        | (Method::GET, ["providers", "proxies", "sub", "healthcheck"])
+        | (Method::PUT, ["providers", "rules", "reject"]) => no_content(),
+        (Method::GET, ["providers", "rules"]) => ok(json!({"providers": {
+            "reject": {"name": "reject", "type": "Rule", "vehicleType": "HTTP",
+                "behavior": "Domain", "format": "YamlRule", "ruleCount": 3}
+        }})),
+        (Method::PUT, ["configs"]) => {
+            let path = body.as_ref().and_then(|b| b["path"].as_str()).unwrap_or("");
+            if path.contains("invalid") {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "yaml: line 3: mapping values are not allowed",
+                );
+            }
+            no_content()
+        }
+        (Method::PATCH, ["configs"]) => no_content(),
+        (Method::GET, ["dns", "query"]) => {
+            let name = query.get("name").map(String::as_str).unwrap_or("");
+            let answer = match query.get("type").map(String::as_str).unwrap_or("A") {
+                "A" => json!([
+                    {"name": format!("{name}."), "type": 5, "TTL": 60, "data": "edge.example.net."},
+                    {"name": "edge.example.net.", "type": 1, "TTL": 60, "data": "93.184.216.34"},
+                ]),
+                "AAAA" => json!([
+                    {"name": format!("{name}."), "type": 28, "TTL": 60, "data": "2606:2800:220:1::248"},
+                ]),
+                _ => return error(StatusCode::BAD_REQUEST, "invalid query type"),
+            };
+            ok(json!({
+                "Status": 0, "TC": false, "RD": true, "RA": true, "AD": false, "CD": false,
+                "Question": [{"Name": format!("{name}."), "Qtype": 1, "Qclass": 1}],
+                "Answer": answer,
+            }))
+        }
+        (Method::POST, ["cache", "fakeip", "flush"]) => no_content(),
+        (Method::GET, ["connections"]) => ok(json!({
+            "downloadTotal": 0,
+            "uploadTotal": 0,
+            "connections": state.lock().unwrap().connections,
+        })),
+        (Method::DELETE, ["connections"]) => {
+            state.lock().unwrap().connections.clear();
+            no_content()
+        }
+        (Method::DELETE, ["connections", id]) => {
+            let mut state = state.lock().unwrap();
+            if state.failing.iter().any(|failing| failing == id) {
+                return error(StatusCode::INTERNAL_SERVER_ERROR, "close failed");
+            }
+            state.connections.retain(|c| c["id"] != *id);
+            no_content()
+        }
+        _ => error(StatusCode::NOT_FOUND, "resource not found"),
+    }
+}
+
+fn proxies(now: &str) -> Value {
+    json!({
+        GROUP: {"name": GROUP, "type": "Selector", "now": now,
+            "all": [NODE, SLOW_NODE, HANGING_NODE, "DIRECT"], "history": []},
+        NODE: {"name": NODE, "type": "Shadowsocks", "history": [{"time": "", "delay": 87}]},
+        SLOW_NODE: {"name": SLOW_NODE, "type": "Vmess", "history": []},
+        HANGING_NODE: {"name": HANGING_NODE, "type": "Trojan", "history": []},
+        "DIRECT": {"name": "DIRECT", "type": "Direct", "history": []},
+    })
+}
+
+fn connection(id: &str, node: &str) -> Value {
+    json!({
+        "id": id,
+        "metadata": {"network": "tcp", "type": "HTTP", "host": "example.com"},
+        "chains": [node, GROUP],
+        "rule": "MATCH",
+        "rulePayload": "",
+    })
+}
+
+fn ok(value: Value) -> Response {
+    warp::reply::json(&value).into_response()
+}
+
+fn no_content() -> Response {
+    StatusCode::NO_CONTENT.into_response()
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    warp::reply::with_status(warp::reply::json(&json!({"message": message})), status)
+        .into_response()
+}
+
+fn unauthorized() -> Response {
+    error(StatusCode::UNAUTHORIZED, "Unauthorized")
+}
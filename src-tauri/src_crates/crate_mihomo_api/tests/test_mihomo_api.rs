@@ -1,29 +1,490 @@
-use mihomo_api;
+mod common;
+
+use common::{GROUP, HANGING_NODE, MockController, NODE, SLOW_NODE};
+use futures::StreamExt;
+use mihomo_api::{Backoff, ConnectionFilter, MihomoError, Timeouts, Traffic};
 use reqwest::header::HeaderMap;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 #[test]
 fn test_mihomo_manager_init() {
     let manager = mihomo_api::MihomoManager::new("url".into(), HeaderMap::new());
-    assert_eq!(manager.get_proxies(), serde_json::Value::Null);
-    assert_eq!(manager.get_providers_proxies(), serde_json::Value::Null);
+    assert!(manager.get_proxies().proxies.is_empty());
+    assert!(manager.get_providers_proxies().providers.is_empty());
+}
+
+#[test]
+fn test_parse_proxies() {
+    let body = r#"{"proxies":{
+        "GLOBAL":{"alive":true,"all":["DIRECT","HK 01"],"extra":{},"hidden":false,
+            "history":[],"icon":"","name":"GLOBAL","now":"HK 01","testUrl":"","tfo":false,
+            "type":"Selector","udp":true,"xudp":false},
+        "HK 01":{"alive":true,"extra":{},"history":[{"time":"2025-03-01T10:00:00+08:00","delay":87}],
+            "id":"","mptcp":false,"name":"HK 01","smux":false,"tfo":false,"type":"Shadowsocks",
+            "udp":true,"uot":false,"xudp":false}
+    }}"#;
+    let proxies: mihomo_api::Proxies = serde_json::from_str(body).unwrap();
+
+    let global = &proxies.proxies["GLOBAL"];
+    assert!(global.is_group());
+    assert_eq!(global.proxy_type, "Selector");
+    assert_eq!(global.now.as_deref(), Some("HK 01"));
+    assert_eq!(global.all.as_ref().unwrap().len(), 2);
+
+    let node = &proxies.proxies["HK 01"];
+    assert!(!node.is_group());
+    assert_eq!(node.last_delay(), Some(87));
+    assert_eq!(node.other["mptcp"], serde_json::json!(false));
+
+    // unknown fields survive a round trip
+    let value = serde_json::to_value(&proxies).unwrap();
+    assert_eq!(value["proxies"]["HK 01"]["smux"], serde_json::json!(false));
+    assert_eq!(value["proxies"]["GLOBAL"]["alive"], serde_json::json!(true));
+}
+
+#[test]
+fn test_parse_providers_proxies() {
+    let body = r#"{"providers":{
+        "sub":{"expectedStatus":"*","name":"sub","proxies":[],"testUrl":"https://www.gstatic.com/generate_204",
+            "type":"Proxy","vehicleType":"HTTP","updatedAt":"2025-03-01T10:00:00.000+08:00",
+            "subscriptionInfo":{"Upload":1,"Download":2,"Total":3,"Expire":4}},
+        "default":{"name":"default","proxies":[],"type":"Proxy","vehicleType":"Compatible"}
+    }}"#;
+    let providers: mihomo_api::ProxyProviders = serde_json::from_str(body).unwrap();
+
+    let sub = &providers.providers["sub"];
+    assert_eq!(sub.vehicle_type, "HTTP");
+    assert_eq!(
+        sub.subscription_info,
+        Some(mihomo_api::SubscriptionInfo {
+            upload: 1,
+            download: 2,
+            total: 3,
+            expire: 4
+        })
+    );
+    assert!(sub.updated_at.is_some());
+    assert_eq!(sub.other["expectedStatus"], serde_json::json!("*"));
+    assert!(providers.providers["default"].subscription_info.is_none());
 }
 
 #[tokio::test]
 async fn test_refresh_proxies() {
-    let manager = mihomo_api::MihomoManager::new("http://127.0.0.1:9097".into(), HeaderMap::new());
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
     let manager = manager.refresh_proxies().await.unwrap();
     let proxies = manager.get_proxies();
     let providers = manager.get_providers_proxies();
-    assert_ne!(proxies, serde_json::Value::Null);
-    assert_eq!(providers, serde_json::Value::Null);
+    assert_eq!(proxies.proxies[GROUP].now.as_deref(), Some(NODE));
+    assert_eq!(proxies.proxies[NODE].last_delay(), Some(87));
+    assert!(providers.providers.is_empty());
 }
 
 #[tokio::test]
 async fn test_refresh_providers_proxies() {
-    let manager = mihomo_api::MihomoManager::new("http://127.0.0.1:9097".into(), HeaderMap::new());
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
     let manager = manager.refresh_providers_proxies().await.unwrap();
     let proxies = manager.get_proxies();
     let providers = manager.get_providers_proxies();
-    assert_eq!(proxies, serde_json::Value::Null);
-    assert_ne!(providers, serde_json::Value::Null);
+    assert!(proxies.proxies.is_empty());
+    assert_eq!(providers.providers["sub"].proxies[0].name, NODE);
+}
+
+#[test]
+fn test_parse_connections() {
+    let body = r#"{"downloadTotal":1024,"uploadTotal":512,"memory":0,"connections":[
+        {"id":"1f3c","metadata":{"network":"tcp","type":"HTTPS","sourceIP":"127.0.0.1",
+            "destinationIP":"142.250.72.14","sourcePort":"51234","destinationPort":"443",
+            "inboundName":"DEFAULT-MIXED","host":"www.google.com","dnsMode":"normal",
+            "process":"curl","processPath":"/usr/bin/curl","sniffHost":""},
+         "upload":100,"download":200,"start":"2025-03-01T10:00:00+08:00",
+         "chains":["HK 01","Proxy"],"rule":"DOMAIN-SUFFIX","rulePayload":"google.com"}
+    ]}"#;
+    let connections: mihomo_api::Connections = serde_json::from_str(body).unwrap();
+    assert_eq!(connections.download_total, 1024);
+    assert_eq!(connections.connections.len(), 1);
+
+    let conn = &connections.connections[0];
+    assert_eq!(conn.metadata.destination_port, "443");
+    assert_eq!(conn.metadata.other["inboundName"], "DEFAULT-MIXED");
+
+    use mihomo_api::ConnectionFilter::*;
+    assert!(Host("WWW.google.com".into()).matches(conn));
+    assert!(Host("142.250.72.14".into()).matches(conn));
+    assert!(Rule("DOMAIN-SUFFIX".into()).matches(conn));
+    assert!(Rule("google.com".into()).matches(conn));
+    assert!(Chain("HK 01".into()).matches(conn));
+    assert!(!Chain("JP 01".into()).matches(conn));
+    assert!(Process("curl".into()).matches(conn));
+    assert!(Process("/usr/bin/curl".into()).matches(conn));
+
+    let idle: mihomo_api::Connections =
+        serde_json::from_str(r#"{"downloadTotal":0,"uploadTotal":0,"connections":null}"#).unwrap();
+    assert!(idle.connections.is_empty());
+}
+
+#[test]
+fn test_parse_stream_messages() {
+    let traffic: mihomo_api::Traffic = serde_json::from_str(r#"{"up":1024,"down":2048}"#).unwrap();
+    assert_eq!(
+        traffic,
+        mihomo_api::Traffic {
+            up: 1024,
+            down: 2048
+        }
+    );
+
+    let memory: mihomo_api::Memory =
+        serde_json::from_str(r#"{"inuse":52428800,"oslimit":0}"#).unwrap();
+    assert_eq!(memory.inuse, 52428800);
+
+    let log: mihomo_api::LogEntry =
+        serde_json::from_str(r#"{"type":"warning","payload":"dial tcp: i/o timeout"}"#).unwrap();
+    assert_eq!(log.level, "warning");
+    assert_eq!(mihomo_api::LogLevel::Warning.as_str(), log.level);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_unix_socket_transport() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let path = std::env::temp_dir().join(format!("mihomo-api-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = tokio::net::UnixListener::bind(&path).unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let _ = stream.read(&mut buf).await.unwrap();
+        let body = r#"{"proxies":{"DIRECT":{"name":"DIRECT","type":"Direct","history":[]}}}"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+    });
+
+    let manager = mihomo_api::MihomoManager::new("http://localhost".into(), HeaderMap::new())
+        .with_transport(mihomo_api::Transport::Unix(path.clone()));
+    let proxies = manager.refresh_proxies().await.unwrap().get_proxies();
+    assert_eq!(proxies.proxies["DIRECT"].proxy_type, "Direct");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_parse_rule_providers() {
+    let body = r#"{"providers":{"reject":{"behavior":"Domain","format":"YamlRule",
+        "name":"reject","ruleCount":120,"type":"Rule","updatedAt":"2025-03-01T10:00:00+08:00",
+        "vehicleType":"HTTP","payload":null}}}"#;
+    let providers: mihomo_api::RuleProviders = serde_json::from_str(body).unwrap();
+    let reject = &providers.providers["reject"];
+    assert_eq!(reject.vehicle_type, "HTTP");
+    assert_eq!(reject.rule_count, 120);
+    assert!(reject.other.contains_key("payload"));
+}
+
+#[tokio::test]
+async fn test_auth_header() {
+    let mock = MockController::start(Some("s3cret")).await;
+
+    let manager = mock.manager(Some("s3cret"));
+    manager.refresh_proxies().await.unwrap();
+    assert_eq!(
+        mock.last_request().authorization.as_deref(),
+        Some("Bearer s3cret")
+    );
+
+    let err = mock
+        .manager(Some("wrong"))
+        .get_connections()
+        .await
+        .unwrap_err();
+    assert_eq!(err, MihomoError::Unauthorized);
+    let err = mock.manager(None).get_connections().await.unwrap_err();
+    assert_eq!(err, MihomoError::Unauthorized);
+}
+
+#[tokio::test]
+async fn test_patch_configs_no_content() {
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
+    manager
+        .patch_configs(serde_json::json!({"mode": "global"}))
+        .await
+        .unwrap();
+
+    let request = mock.last_request();
+    assert_eq!(request.method, "PATCH");
+    assert_eq!(request.segments, ["configs"]);
+    assert_eq!(request.body, Some(serde_json::json!({"mode": "global"})));
+}
+
+#[tokio::test]
+async fn test_put_configs_force() {
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
+    manager
+        .put_configs_force("/tmp/clash-verge.yaml")
+        .await
+        .unwrap();
+
+    let request = mock.last_request();
+    assert_eq!(request.method, "PUT");
+    assert_eq!(request.segments, ["configs"]);
+    assert_eq!(request.query["force"], "true");
+    assert_eq!(
+        request.body,
+        Some(serde_json::json!({"path": "/tmp/clash-verge.yaml"}))
+    );
+
+    let err = manager
+        .put_configs_force("/tmp/invalid.yaml")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, MihomoError::BadRequest(message) if message.starts_with("yaml:")));
+}
+
+#[tokio::test]
+async fn test_select_proxy() {
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
+    manager.select_proxy(GROUP, "DIRECT").await.unwrap();
+    assert_eq!(mock.now(), "DIRECT");
+
+    let err = manager.select_proxy(GROUP, "JP 01").await.unwrap_err();
+    assert!(matches!(err, MihomoError::BadRequest(_)));
+    let err = manager.select_proxy("Unknown", "DIRECT").await.unwrap_err();
+    assert!(matches!(err, MihomoError::NotFound(_)));
+}
+
+#[tokio::test]
+async fn test_proxy_delay() {
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
+
+    let delay = manager
+        .test_proxy_delay(NODE, Some("https://example.com/a?b=1&c=2".into()), 1000)
+        .await
+        .unwrap();
+    assert_eq!(delay.delay, 87);
+    let request = mock.last_request();
+    assert_eq!(request.segments, ["proxies", NODE, "delay"]);
+    assert_eq!(request.query["url"], "https://example.com/a?b=1&c=2");
+    assert_eq!(request.query["timeout"], "1000");
+
+    // the core gave up on the node
+    let err = manager
+        .test_proxy_delay(SLOW_NODE, None, 50)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, MihomoError::Status { code: 504, .. }));
+    assert_eq!(
+        mock.last_request().query["url"],
+        mihomo_api::DEFAULT_TEST_URL
+    );
+
+    // the core did not answer within the test timeout plus the margin
+    let manager = manager.with_timeouts(Timeouts {
+        delay_margin: Duration::from_millis(100),
+        ..Timeouts::default()
+    });
+    let err = manager
+        .test_proxy_delay(HANGING_NODE, None, 50)
+        .await
+        .unwrap_err();
+    assert_eq!(err, MihomoError::Timeout);
+}
+
+#[tokio::test]
+async fn test_group_and_batch_delay() {
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
+
+    let delays = manager
+        .test_group_delay(GROUP, None, 1000, Some("204".into()))
+        .await
+        .unwrap();
+    assert_eq!(delays[NODE], 87);
+    assert_eq!(mock.last_request().query["expected"], "204");
+
+    let names = vec![
+        SLOW_NODE.to_string(),
+        NODE.to_string(),
+        "DIRECT".to_string(),
+    ];
+    let results: Vec<_> = manager
+        .test_proxies_delay(names, None, 200, 2)
+        .collect()
+        .await;
+    assert_eq!(results.len(), 3);
+    // the slow node finishes last
+    assert_eq!(results[2].0, SLOW_NODE);
+    assert!(results[2].1.is_err());
+    assert!(results[..2].iter().all(|(_, r)| r.is_ok()));
+}
+
+#[tokio::test]
+async fn test_providers() {
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
+
+    manager.update_proxy_provider("sub").await.unwrap();
+    assert_eq!(mock.last_request().method, "PUT");
+    manager.healthcheck_proxy_provider("sub").await.unwrap();
+    assert_eq!(
+        mock.last_request().segments,
+        ["providers", "proxies", "sub", "healthcheck"]
+    );
+
+    let rules = manager.get_rule_providers().await.unwrap();
+    assert_eq!(rules.providers["reject"].rule_count, 3);
+    manager.update_rule_provider("reject").await.unwrap();
+
+    let err = manager.update_rule_provider("missing").await.unwrap_err();
+    assert!(matches!(err, MihomoError::NotFound(_)));
+}
+
+#[tokio::test]
+async fn test_close_connections() {
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
+
+    let closed = manager.close_connections_by_chain(NODE).await.unwrap();
+    assert_eq!(closed, 1);
+    assert_eq!(mock.connection_ids(), ["c2"]);
+
+    manager
+        .close_connections(&ConnectionFilter::Host("nothing.example".into()))
+        .await
+        .unwrap();
+    assert_eq!(mock.connection_ids(), ["c2"]);
+
+    manager.close_all_connections().await.unwrap();
+    assert!(mock.connection_ids().is_empty());
+}
+
+#[tokio::test]
+async fn test_close_connections_keeps_going() {
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
+    mock.fail_close("c1");
+
+    let err = manager.close_connections_by_chain(GROUP).await.unwrap_err();
+    assert!(err.to_string().contains("1 of 2"), "{err}");
+    assert_eq!(mock.connection_ids(), ["c1"]);
+}
+
+#[tokio::test]
+async fn test_dns_query() {
+    let mock = MockController::start(None).await;
+    let manager = mock.manager(None);
+
+    let answer = manager.query_dns("example.com", None).await.unwrap();
+    assert_eq!(answer.status, 0);
+    assert_eq!(answer.question[0].name, "example.com.");
+    assert_eq!(answer.answer.len(), 2);
+    assert_eq!(
+        answer.addresses(),
+        ["93.184.216.34".parse::<std::net::IpAddr>().unwrap()]
+    );
+    let request = mock.last_request();
+    assert_eq!(request.query["name"], "example.com");
+    assert_eq!(request.query["type"], "A");
+
+    let answer = manager
+        .query_dns("example.com", Some("AAAA"))
+        .await
+        .unwrap();
+    assert!(answer.addresses()[0].is_ipv6());
+
+    let err = manager.query_dns("example.com", Some("BAD")).await;
+    assert!(matches!(err, Err(MihomoError::BadRequest(_))));
+
+    manager.flush_fakeip().await.unwrap();
+    let request = mock.last_request();
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.segments, ["cache", "fakeip", "flush"]);
+}
+
+#[tokio::test]
+async fn test_error_kinds() {
+    let mock = MockController::start(None).await;
+    mock.serve_html();
+    let err = mock.manager(None).refresh_proxies().await.err().unwrap();
+    assert!(matches!(err, MihomoError::InvalidBody(_)));
+
+    // nothing listens on the port of a dropped listener
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    let manager = mihomo_api::MihomoManager::new(url, HeaderMap::new());
+    let err = manager.refresh_proxies().await.err().unwrap();
+    assert!(err.is_unreachable());
+}
+
+#[tokio::test]
+async fn test_traffic_stream() {
+    let mock = MockController::start(Some("s3cret")).await;
+    let traffic: Vec<_> = mock
+        .manager(Some("s3cret"))
+        .traffic_stream()
+        .take(3)
+        .collect()
+        .await;
+    assert_eq!(traffic[2], mihomo_api::Traffic { up: 3, down: 30 });
+}
+
+#[tokio::test]
+async fn test_ws_stream_follows_manager() {
+    let mock = MockController::start(Some("s3cret")).await;
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let dead = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    // the first attempts go to a port nobody listens on, then to the controller
+    let target = Arc::new(Mutex::new(mihomo_api::MihomoManager::new(
+        dead,
+        HeaderMap::new(),
+    )));
+    let source = target.clone();
+    let backoff = Backoff {
+        initial: Duration::from_millis(10),
+        max: Duration::from_millis(20),
+    };
+    let stream = mihomo_api::MihomoManager::ws_stream_from::<Traffic, _>(
+        move || source.lock().unwrap().clone(),
+        "/traffic",
+        backoff,
+    );
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    *target.lock().unwrap() = mock.manager(Some("s3cret"));
+
+    let traffic: Vec<Traffic> =
+        tokio::time::timeout(Duration::from_secs(5), stream.take(1).collect())
+            .await
+            .unwrap();
+    assert_eq!(traffic[0], Traffic { up: 1, down: 10 });
+}
+
+#[tokio::test]
+async fn test_ws_stream_backs_off_when_closed_at_once() {
+    let mock = MockController::start(None).await;
+    let backoff = Backoff {
+        initial: Duration::from_millis(100),
+        max: Duration::from_secs(1),
+    };
+    let stream = mock.manager(None).ws_stream::<Traffic>("/closing", backoff);
+    let _ = tokio::time::timeout(
+        Duration::from_millis(500),
+        stream.take(1).collect::<Vec<_>>(),
+    )
+    .await;
+
+    // 100 + 200 ms of waiting fit in, a busy loop would connect hundreds of times
+    assert!(mock.closing_upgrades() <= 4, "{}", mock.closing_upgrades());
 }
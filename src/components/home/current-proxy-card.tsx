@@ -26,14 +26,9 @@ import {
 import { useNavigate } from "react-router-dom";
 import { useCurrentProxy } from "@/hooks/use-current-proxy";
 import { EnhancedCard } from "@/components/home/enhanced-card";
-import {
-  getProxies,
-  updateProxy,
-  getConnections,
-  deleteConnection,
-} from "@/services/api";
+import { getProxies } from "@/services/api";
+import { selectProxy } from "@/services/cmds";
 import delayManager from "@/services/delay";
-import { useVerge } from "@/hooks/use-verge";
 
 // 本地存储的键名
 const STORAGE_KEY_GROUP = "clash-verge-selected-proxy-group";
@@ -90,7 +85,6 @@ export const CurrentProxyCard = () => {
   const { currentProxy, primaryGroupName, mode, refreshProxy } = useCurrentProxy();
   const navigate = useNavigate();
   const theme = useTheme();
-  const { verge } = useVerge();
 
   // 判断模式
   const isGlobalMode = mode === "global";
@@ -343,7 +337,6 @@ export const CurrentProxyCard = () => {
     
     const newProxy = event.target.value;
     const currentGroup = state.selection.group;
-    const previousProxy = state.selection.proxy;
     
     // 立即更新UI，优化体验
     debouncedSetState((prev: ProxyState) => ({
@@ -361,19 +354,8 @@ export const CurrentProxyCard = () => {
     }
     
     try {
-      // 更新代理设置
-      await updateProxy(currentGroup, newProxy);
-      
-      // 自动关闭连接设置
-      if (verge?.auto_close_connection && previousProxy) {
-        getConnections().then(({ connections }) => {
-          connections.forEach(conn => {
-            if (conn.chains.includes(previousProxy)) {
-              deleteConnection(conn.id);
-            }
-          });
-        });
-      }
+      // 更新代理设置，断开连接由后端根据 auto_close_connection 处理
+      await selectProxy(currentGroup, newProxy);
       
       // 刷新代理信息，使用较短的延迟
       setTimeout(() => {
@@ -383,7 +365,7 @@ export const CurrentProxyCard = () => {
     } catch (error) {
       console.error("更新代理失败", error);
     }
-  }, [isDirectMode, isGlobalMode, state.proxyData.records, state.selection, verge?.auto_close_connection, refreshProxy, fetchProxyData, debouncedSetState]);
+  }, [isDirectMode, isGlobalMode, state.proxyData.records, state.selection, refreshProxy, fetchProxyData, debouncedSetState]);
 
   // 导航到代理页面
   const goToProxies = useCallback(() => {
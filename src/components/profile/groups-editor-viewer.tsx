@@ -86,6 +86,7 @@ export const GroupsEditorViewer = (props: Props) => {
   const [prependSeq, setPrependSeq] = useState<IProxyGroupConfig[]>([]);
   const [appendSeq, setAppendSeq] = useState<IProxyGroupConfig[]>([]);
   const [deleteSeq, setDeleteSeq] = useState<string[]>([]);
+  const [replaceSeq, setReplaceSeq] = useState<ISeqReplace[]>([]);
 
   const filteredPrependSeq = useMemo(
     () => prependSeq.filter((group) => match(group.name)),
@@ -160,6 +161,7 @@ export const GroupsEditorViewer = (props: Props) => {
     setPrependSeq(obj?.prepend || []);
     setAppendSeq(obj?.append || []);
     setDeleteSeq(obj?.delete || []);
+    setReplaceSeq(obj?.replace || []);
 
     setPrevData(data);
     setCurrData(data);
@@ -173,23 +175,31 @@ export const GroupsEditorViewer = (props: Props) => {
       prepend: [];
       append: [];
       delete: [];
+      replace?: ISeqReplace[];
     } | null;
     setPrependSeq(obj?.prepend || []);
     setAppendSeq(obj?.append || []);
     setDeleteSeq(obj?.delete || []);
+    setReplaceSeq(obj?.replace || []);
   }, [visualization]);
 
   useEffect(() => {
     if (prependSeq && appendSeq && deleteSeq)
       setCurrData(
         yaml.dump(
-          { prepend: prependSeq, append: appendSeq, delete: deleteSeq },
+          {
+            prepend: prependSeq,
+            append: appendSeq,
+            delete: deleteSeq,
+            // the pattern replacements have no visual editor, keep them
+            ...(replaceSeq.length > 0 && { replace: replaceSeq }),
+          },
           {
             forceQuotes: true,
           }
         )
       );
-  }, [prependSeq, appendSeq, deleteSeq]);
+  }, [prependSeq, appendSeq, deleteSeq, replaceSeq]);
 
   const fetchProxyPolicy = async () => {
     let data = await readProfileFile(profileUid);
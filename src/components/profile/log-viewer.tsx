@@ -14,18 +14,19 @@ import { BaseEmpty } from "@/components/base";
 
 interface Props {
   open: boolean;
+  title?: string;
   logInfo: [string, string][];
   onClose: () => void;
 }
 
 export const LogViewer = (props: Props) => {
-  const { open, logInfo, onClose } = props;
+  const { open, title, logInfo, onClose } = props;
 
   const { t } = useTranslation();
 
   return (
     <Dialog open={open} onClose={onClose}>
-      <DialogTitle>{t("Script Console")}</DialogTitle>
+      <DialogTitle>{title ?? t("Script Console")}</DialogTitle>
 
       <DialogContent
         sx={{
@@ -32,6 +32,8 @@ import parseTraffic from "@/utils/parse-traffic";
 import { ConfirmViewer } from "@/components/profile/confirm-viewer";
 import { open } from "@tauri-apps/plugin-shell";
 import { ProxiesEditorViewer } from "./proxies-editor-viewer";
+import { LogViewer } from "./log-viewer";
+import { VersionViewer } from "./version-viewer";
 const round = keyframes`
   from { transform: rotate(0deg); }
   to { transform: rotate(360deg); }
@@ -42,6 +44,7 @@ interface Props {
   selected: boolean;
   activating: boolean;
   itemData: IProfileItem;
+  logInfo?: [string, string][];
   onSelect: (force: boolean) => void;
   onEdit: () => void;
   onSave?: (prev?: string, curr?: string) => void;
@@ -49,8 +52,16 @@ interface Props {
 }
 
 export const ProfileItem = (props: Props) => {
-  const { selected, activating, itemData, onSelect, onEdit, onSave, onDelete } =
-    props;
+  const {
+    selected,
+    activating,
+    itemData,
+    logInfo = [],
+    onSelect,
+    onEdit,
+    onSave,
+    onDelete,
+  } = props;
   const {
     attributes,
     listeners,
@@ -66,7 +77,14 @@ export const ProfileItem = (props: Props) => {
   const loadingCache = useLoadingCache();
   const setLoadingCache = useSetLoadingCache();
 
-  const { uid, name = "Profile", extra, updated = 0, option } = itemData;
+  const {
+    uid,
+    name = "Profile",
+    extra,
+    updated = 0,
+    option,
+    fetch: lastFetch,
+  } = itemData;
 
   // local file mode
   // remote file mode
@@ -86,6 +104,15 @@ export const ProfileItem = (props: Props) => {
 
   const loading = loadingCache[itemData.uid] ?? false;
 
+  // how the last update went, shown with the update time
+  const fetchInfo = [
+    lastFetch?.path &&
+      `${t("Fetch Path")}: ${t(`Fetch Path-${lastFetch.path}`)}`,
+    ...(lastFetch?.errors ?? []),
+  ]
+    .filter(Boolean)
+    .join("\n");
+
   // interval update fromNow field
   const [, setRefresh] = useState({});
   useEffect(() => {
@@ -119,8 +146,11 @@ export const ProfileItem = (props: Props) => {
   const [proxiesOpen, setProxiesOpen] = useState(false);
   const [groupsOpen, setGroupsOpen] = useState(false);
   const [mergeOpen, setMergeOpen] = useState(false);
+  const [transformOpen, setTransformOpen] = useState(false);
   const [scriptOpen, setScriptOpen] = useState(false);
   const [confirmOpen, setConfirmOpen] = useState(false);
+  const [logOpen, setLogOpen] = useState(false);
+  const [versionOpen, setVersionOpen] = useState(false);
 
   const onOpenHome = () => {
     setAnchorEl(null);
@@ -157,11 +187,26 @@ export const ProfileItem = (props: Props) => {
     setMergeOpen(true);
   };
 
+  const onEditTransform = () => {
+    setAnchorEl(null);
+    setTransformOpen(true);
+  };
+
   const onEditScript = () => {
     setAnchorEl(null);
     setScriptOpen(true);
   };
 
+  const onViewLogs = () => {
+    setAnchorEl(null);
+    setLogOpen(true);
+  };
+
+  const onViewVersions = () => {
+    setAnchorEl(null);
+    setVersionOpen(true);
+  };
+
   const onForceSelect = () => {
     setAnchorEl(null);
     onSelect(true);
@@ -235,6 +280,11 @@ export const ProfileItem = (props: Props) => {
       handler: onEditGroups,
       disabled: !option?.groups,
     },
+    {
+      label: "Edit Transform",
+      handler: onEditTransform,
+      disabled: !option?.transform,
+    },
     {
       label: "Extend Config",
       handler: onEditMerge,
@@ -246,6 +296,12 @@ export const ProfileItem = (props: Props) => {
       disabled: !option?.script,
     },
     { label: "Open File", handler: onOpenFile, disabled: false },
+    {
+      label: "Profile Warnings",
+      handler: onViewLogs,
+      disabled: logInfo.length === 0,
+    },
+    { label: "Versions", handler: onViewVersions, disabled: false },
     { label: "Update", handler: () => onUpdate(0), disabled: false },
     { label: "Update(Proxy)", handler: () => onUpdate(2), disabled: false },
     {
@@ -276,6 +332,11 @@ export const ProfileItem = (props: Props) => {
       handler: onEditGroups,
       disabled: !option?.groups,
     },
+    {
+      label: "Edit Transform",
+      handler: onEditTransform,
+      disabled: !option?.transform,
+    },
     {
       label: "Extend Config",
       handler: onEditMerge,
@@ -287,6 +348,11 @@ export const ProfileItem = (props: Props) => {
       disabled: !option?.script,
     },
     { label: "Open File", handler: onOpenFile, disabled: false },
+    {
+      label: "Profile Warnings",
+      handler: onViewLogs,
+      disabled: logInfo.length === 0,
+    },
     {
       label: "Delete",
       handler: () => {
@@ -419,7 +485,10 @@ export const ProfileItem = (props: Props) => {
                   flex="1 0 auto"
                   fontSize={14}
                   textAlign="right"
-                  title={`${t("Update Time")}: ${parseExpire(updated)}`}
+                  title={`${t("Update Time")}: ${parseExpire(updated)}${
+                    fetchInfo ? `\n${fetchInfo}` : ""
+                  }`}
+                  color={lastFetch && !lastFetch.path ? "error" : undefined}
                 >
                   {updated > 0 ? dayjs(updated * 1000).fromNow() : ""}
                 </Typography>
@@ -543,6 +612,18 @@ export const ProfileItem = (props: Props) => {
           onClose={() => setMergeOpen(false)}
         />
       )}
+      {transformOpen && (
+        <EditorViewer
+          open={true}
+          initialData={readProfileFile(option?.transform ?? "")}
+          language="yaml"
+          onSave={async (prev, curr) => {
+            await saveProfileFile(option?.transform ?? "", curr ?? "");
+            onSave && onSave(prev, curr);
+          }}
+          onClose={() => setTransformOpen(false)}
+        />
+      )}
       {scriptOpen && (
         <EditorViewer
           open={true}
@@ -556,6 +637,23 @@ export const ProfileItem = (props: Props) => {
         />
       )}
 
+      {logOpen && (
+        <LogViewer
+          open={logOpen}
+          logInfo={logInfo}
+          onClose={() => setLogOpen(false)}
+        />
+      )}
+
+      {versionOpen && (
+        <VersionViewer
+          open={versionOpen}
+          uid={uid}
+          onClose={() => setVersionOpen(false)}
+          onRestore={() => mutate("getProfiles")}
+        />
+      )}
+
       <ConfirmViewer
         title={t("Confirm deletion")}
         message={t("This operation is not reversible")}
@@ -19,6 +19,7 @@ import {
   TextField,
 } from "@mui/material";
 import { createProfile, patchProfile } from "@/services/cmds";
+import { useProfiles } from "@/hooks/use-profiles";
 import { BaseDialog, Notice, Switch } from "@/components/base";
 import { version } from "@root/package.json";
 import { FileInput } from "./file-input";
@@ -33,10 +34,11 @@ export interface ProfileViewerRef {
 }
 
 // create or edit the profile
-// remote / local
+// remote / local / aggregate
 export const ProfileViewer = forwardRef<ProfileViewerRef, Props>(
   (props, ref) => {
     const { t } = useTranslation();
+    const { profiles } = useProfiles();
     const [open, setOpen] = useState(false);
     const [openType, setOpenType] = useState<"new" | "edit">("new");
     const [loading, setLoading] = useState(false);
@@ -143,6 +145,12 @@ export const ProfileViewer = forwardRef<ProfileViewerRef, Props>(
     const formType = watch("type");
     const isRemote = formType === "remote";
     const isLocal = formType === "local";
+    const isAggregate = formType === "aggregate";
+
+    // an aggregate profile merges remote and local profiles
+    const sourceItems = (profiles?.items ?? []).filter(
+      (item) => item.type === "remote" || item.type === "local",
+    );
 
     return (
       <BaseDialog
@@ -165,6 +173,7 @@ export const ProfileViewer = forwardRef<ProfileViewerRef, Props>(
               <Select {...field} autoFocus label={t("Type")}>
                 <MenuItem value="remote">Remote</MenuItem>
                 <MenuItem value="local">Local</MenuItem>
+                <MenuItem value="aggregate">Aggregate</MenuItem>
               </Select>
             </FormControl>
           )}
@@ -216,6 +225,39 @@ export const ProfileViewer = forwardRef<ProfileViewerRef, Props>(
           </>
         )}
 
+        {isAggregate && (
+          <Controller
+            name="option.sources"
+            control={control}
+            render={({ field }) => (
+              <FormControl size="small" fullWidth sx={{ mt: 2, mb: 1 }}>
+                <InputLabel>{t("Aggregated Profiles")}</InputLabel>
+                <Select
+                  {...field}
+                  multiple
+                  value={field.value ?? []}
+                  label={t("Aggregated Profiles")}
+                  renderValue={(uids) =>
+                    uids
+                      .map(
+                        (uid) =>
+                          sourceItems.find((item) => item.uid === uid)?.name ??
+                          uid,
+                      )
+                      .join(", ")
+                  }
+                >
+                  {sourceItems.map((item) => (
+                    <MenuItem key={item.uid} value={item.uid}>
+                      {item.name}
+                    </MenuItem>
+                  ))}
+                </Select>
+              </FormControl>
+            )}
+          />
+        )}
+
         {(isRemote || isLocal) && (
           <Controller
             name="option.update_interval"
@@ -63,6 +63,7 @@ export const ProxiesEditorViewer = (props: Props) => {
   const [prependSeq, setPrependSeq] = useState<IProxyConfig[]>([]);
   const [appendSeq, setAppendSeq] = useState<IProxyConfig[]>([]);
   const [deleteSeq, setDeleteSeq] = useState<string[]>([]);
+  const [replaceSeq, setReplaceSeq] = useState<ISeqReplace[]>([]);
 
   const filteredPrependSeq = useMemo(
     () => prependSeq.filter((proxy) => match(proxy.name)),
@@ -172,6 +173,7 @@ export const ProxiesEditorViewer = (props: Props) => {
     setPrependSeq(obj?.prepend || []);
     setAppendSeq(obj?.append || []);
     setDeleteSeq(obj?.delete || []);
+    setReplaceSeq(obj?.replace || []);
 
     setPrevData(data);
     setCurrData(data);
@@ -185,23 +187,31 @@ export const ProxiesEditorViewer = (props: Props) => {
       prepend: [];
       append: [];
       delete: [];
+      replace?: ISeqReplace[];
     } | null;
     setPrependSeq(obj?.prepend || []);
     setAppendSeq(obj?.append || []);
     setDeleteSeq(obj?.delete || []);
+    setReplaceSeq(obj?.replace || []);
   }, [visualization]);
 
   useEffect(() => {
     if (prependSeq && appendSeq && deleteSeq)
       setCurrData(
         yaml.dump(
-          { prepend: prependSeq, append: appendSeq, delete: deleteSeq },
+          {
+            prepend: prependSeq,
+            append: appendSeq,
+            delete: deleteSeq,
+            // the pattern replacements have no visual editor, keep them
+            ...(replaceSeq.length > 0 && { replace: replaceSeq }),
+          },
           {
             forceQuotes: true,
           }
         )
       );
-  }, [prependSeq, appendSeq, deleteSeq]);
+  }, [prependSeq, appendSeq, deleteSeq, replaceSeq]);
 
   useEffect(() => {
     if (!open) return;
@@ -256,6 +256,7 @@ export const RulesEditorViewer = (props: Props) => {
   const [prependSeq, setPrependSeq] = useState<string[]>([]);
   const [appendSeq, setAppendSeq] = useState<string[]>([]);
   const [deleteSeq, setDeleteSeq] = useState<string[]>([]);
+  const [replaceSeq, setReplaceSeq] = useState<ISeqReplace[]>([]);
 
   const filteredPrependSeq = useMemo(
     () => prependSeq.filter((rule) => match(rule)),
@@ -309,6 +310,7 @@ export const RulesEditorViewer = (props: Props) => {
     setPrependSeq(obj?.prepend || []);
     setAppendSeq(obj?.append || []);
     setDeleteSeq(obj?.delete || []);
+    setReplaceSeq(obj?.replace || []);
 
     setPrevData(data);
     setCurrData(data);
@@ -322,19 +324,26 @@ export const RulesEditorViewer = (props: Props) => {
     setPrependSeq(obj?.prepend || []);
     setAppendSeq(obj?.append || []);
     setDeleteSeq(obj?.delete || []);
+    setReplaceSeq(obj?.replace || []);
   }, [visualization]);
 
   useEffect(() => {
     if (prependSeq && appendSeq && deleteSeq)
       setCurrData(
         yaml.dump(
-          { prepend: prependSeq, append: appendSeq, delete: deleteSeq },
+          {
+            prepend: prependSeq,
+            append: appendSeq,
+            delete: deleteSeq,
+            // the pattern replacements have no visual editor, keep them
+            ...(replaceSeq.length > 0 && { replace: replaceSeq }),
+          },
           {
             forceQuotes: true,
           }
         )
       );
-  }, [prependSeq, appendSeq, deleteSeq]);
+  }, [prependSeq, appendSeq, deleteSeq, replaceSeq]);
 
   const fetchProfile = async () => {
     let data = await readProfileFile(profileUid); // 原配置文件
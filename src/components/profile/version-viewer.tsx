@@ -0,0 +1,132 @@
+import dayjs from "dayjs";
+import { Fragment, useEffect, useState } from "react";
+import { useLockFn } from "ahooks";
+import { useTranslation } from "react-i18next";
+import {
+  Box,
+  Button,
+  Dialog,
+  DialogActions,
+  DialogContent,
+  DialogTitle,
+  Divider,
+  Typography,
+} from "@mui/material";
+import { BaseEmpty, Notice } from "@/components/base";
+import {
+  diffProfileVersion,
+  getProfileVersions,
+  restoreProfileVersion,
+} from "@/services/cmds";
+import parseTraffic from "@/utils/parse-traffic";
+
+interface Props {
+  open: boolean;
+  uid: string;
+  onClose: () => void;
+  onRestore?: () => void;
+}
+
+// fetched versions of a remote profile
+export const VersionViewer = (props: Props) => {
+  const { open, uid, onClose, onRestore } = props;
+
+  const { t } = useTranslation();
+  const [versions, setVersions] = useState<IProfileVersion[]>([]);
+  const [diffs, setDiffs] = useState<Record<string, IProfileDiff>>({});
+
+  useEffect(() => {
+    if (!open) return;
+    getProfileVersions(uid)
+      .then(setVersions)
+      .catch((err) => Notice.error(err?.message || err.toString()));
+  }, [open, uid]);
+
+  const onDiff = useLockFn(async (id: string) => {
+    try {
+      const diff = await diffProfileVersion(uid, id);
+      setDiffs((diffs) => ({ ...diffs, [id]: diff }));
+    } catch (err: any) {
+      Notice.error(err?.message || err.toString());
+    }
+  });
+
+  const onRestoreVersion = useLockFn(async (id: string) => {
+    try {
+      await restoreProfileVersion(uid, id);
+      Notice.success(t("Version Restored"));
+      onRestore?.();
+      onClose();
+    } catch (err: any) {
+      Notice.error(err?.message || err.toString());
+    }
+  });
+
+  const summary = (changes: IProfileChanges) =>
+    [
+      changes.added.length > 0 && `+${changes.added.length}`,
+      changes.removed.length > 0 && `-${changes.removed.length}`,
+      changes.changed.length > 0 && `~${changes.changed.length}`,
+    ]
+      .filter(Boolean)
+      .join(" ") || "=";
+
+  return (
+    <Dialog open={open} onClose={onClose}>
+      <DialogTitle>{t("Profile Versions")}</DialogTitle>
+
+      <DialogContent sx={{ width: 420, height: 320, pb: 1 }}>
+        {versions.map((version) => {
+          const diff = diffs[version.id];
+          return (
+            <Fragment key={version.id}>
+              <Box display="flex" alignItems="center" gap={1}>
+                <Typography flex={1}>
+                  {dayjs(version.updated * 1000).format("YYYY-MM-DD HH:mm:ss")}
+                </Typography>
+                <Typography color="text.secondary" fontSize={12}>
+                  {parseTraffic(version.size).join("")}
+                </Typography>
+                <Button size="small" onClick={() => onDiff(version.id)}>
+                  {t("Diff")}
+                </Button>
+                <Button
+                  size="small"
+                  variant="outlined"
+                  onClick={() => onRestoreVersion(version.id)}
+                >
+                  {t("Restore")}
+                </Button>
+              </Box>
+              {diff && (
+                <Typography
+                  color="text.secondary"
+                  fontSize={12}
+                  sx={{ userSelect: "text" }}
+                  title={[
+                    ...diff.proxies.added.map((name) => `+ ${name}`),
+                    ...diff.proxies.removed.map((name) => `- ${name}`),
+                    ...diff.proxies.changed.map((name) => `~ ${name}`),
+                  ].join("\n")}
+                >
+                  {t("Proxies")}: {summary(diff.proxies)} | {t("Proxy Groups")}
+                  : {summary(diff.groups)} | {t("Rules")}: {diff.rules[0]} →{" "}
+                  {diff.rules[1]}
+                </Typography>
+              )}
+              <Divider sx={{ my: 0.5 }} />
+            </Fragment>
+          );
+        })}
+
+        {versions.length === 0 && <BaseEmpty />}
+      </DialogContent>
+
+      <DialogActions>
+        <Button onClick={onClose} variant="outlined">
+          {t("Close")}
+        </Button>
+      </DialogActions>
+    </Dialog>
+  );
+};
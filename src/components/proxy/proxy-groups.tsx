@@ -1,14 +1,8 @@
 import { useRef, useState, useEffect, useCallback, useMemo } from "react";
 import { useLockFn } from "ahooks";
 import { Virtuoso, type VirtuosoHandle } from "react-virtuoso";
-import {
-  getConnections,
-  providerHealthCheck,
-  updateProxy,
-  deleteConnection,
-  getGroupProxyDelays,
-} from "@/services/api";
-import { useProfiles } from "@/hooks/use-profiles";
+import { providerHealthCheck, getGroupProxyDelays } from "@/services/api";
+import { selectProxy } from "@/services/cmds";
 import { useVerge } from "@/hooks/use-verge";
 import { BaseEmpty } from "../base";
 import { useRenderList } from "./use-render-list";
@@ -197,7 +191,6 @@ export const ProxyGroups = (props: Props) => {
   const { renderList, onProxies, onHeadState } = useRenderList(mode);
 
   const { verge } = useVerge();
-  const { current, patchCurrent } = useProfiles();
   const timeout = verge?.default_latency_timeout || 10000;
 
   const virtuosoRef = useRef<VirtuosoHandle>(null);
@@ -331,35 +324,11 @@ export const ProxyGroups = (props: Props) => {
     async (group: IProxyGroupItem, proxy: IProxyItem) => {
       if (!["Selector", "URLTest", "Fallback"].includes(group.type)) return;
 
-      const { name, now } = group;
-      await updateProxy(name, proxy.name);
+      const { name } = group;
+      // 断开连接由后端根据 auto_close_connection 处理
+      await selectProxy(name, proxy.name);
+      // 选择结果由后端记录到当前订阅，内核重启后自动恢复
       onProxies();
-
-      // 断开连接
-      if (verge?.auto_close_connection) {
-        getConnections().then(({ connections }) => {
-          connections.forEach((conn) => {
-            if (conn.chains.includes(now!)) {
-              deleteConnection(conn.id);
-            }
-          });
-        });
-      }
-
-      // 保存到selected中
-      if (!current) return;
-      if (!current.selected) current.selected = [];
-
-      const index = current.selected.findIndex(
-        (item) => item.name === group.name,
-      );
-
-      if (index < 0) {
-        current.selected.push({ name, now: proxy.name });
-      } else {
-        current.selected[index] = { name, now: proxy.name };
-      }
-      await patchCurrent({ selected: current.selected });
     },
   );
 
@@ -1,14 +1,31 @@
 import { forwardRef, useImperativeHandle, useState } from "react";
 import { useTranslation } from "react-i18next";
 import { Box, Chip } from "@mui/material";
-import { getRuntimeYaml } from "@/services/cmds";
-import { DialogRef } from "@/components/base";
+import { useLockFn } from "ahooks";
+import { writeText } from "@tauri-apps/plugin-clipboard-manager";
+import {
+  exportRuntimeConfig,
+  getRuntimeDiagnostics,
+  getRuntimeYaml,
+} from "@/services/cmds";
+import { DialogRef, Notice } from "@/components/base";
 import { EditorViewer } from "@/components/profile/editor-viewer";
+import { LogViewer } from "@/components/profile/log-viewer";
+import { TraceViewer } from "./trace-viewer";
+
+const EXPORT_FORMATS: [IExportFormat, string][] = [
+  ["sing-box", "sing-box"],
+  ["share-links", "Share Links"],
+  ["base64", "Base64"],
+];
 
 export const ConfigViewer = forwardRef<DialogRef>((_, ref) => {
   const { t } = useTranslation();
   const [open, setOpen] = useState(false);
   const [runtimeConfig, setRuntimeConfig] = useState("");
+  const [diagnostics, setDiagnostics] = useState<IDiagnostic[]>([]);
+  const [diagnosticOpen, setDiagnosticOpen] = useState(false);
+  const [traceOpen, setTraceOpen] = useState(false);
 
   useImperativeHandle(ref, () => ({
     open: () => {
@@ -16,25 +33,82 @@ export const ConfigViewer = forwardRef<DialogRef>((_, ref) => {
         setRuntimeConfig(data ?? "# Error getting runtime yaml\n");
         setOpen(true);
       });
+      getRuntimeDiagnostics()
+        .then(setDiagnostics)
+        .catch(() => setDiagnostics([]));
     },
     close: () => setOpen(false),
   }));
 
+  // 复制为其他客户端的格式
+  const onExport = useLockFn(async (format: IExportFormat) => {
+    try {
+      const { data, warnings } = await exportRuntimeConfig(format);
+      await writeText(data);
+      Notice.success(t("Copy Success"));
+      if (warnings.length > 0) {
+        Notice.info(warnings.join("\n"), 4000);
+      }
+    } catch (err: any) {
+      Notice.error(err?.message || err.toString());
+    }
+  });
+
+  const hasError = diagnostics.some((d) => d.level === "error");
+
   if (!open) return null;
   return (
-    <EditorViewer
-      open={true}
-      title={
-        <Box display="flex" alignItems="center" gap={2}>
-          {t("Runtime Config")}
-          <Chip label={t("ReadOnly")} size="small" />
-        </Box>
-      }
-      initialData={Promise.resolve(runtimeConfig)}
-      readOnly
-      language="yaml"
-      schema="clash"
-      onClose={() => setOpen(false)}
-    />
+    <>
+      <EditorViewer
+        open={true}
+        title={
+          <Box display="flex" alignItems="center" gap={2}>
+            {t("Runtime Config")}
+            <Chip label={t("ReadOnly")} size="small" />
+            {diagnostics.length > 0 && (
+              <Chip
+                label={t("Config Problems _n", { n: diagnostics.length })}
+                size="small"
+                color={hasError ? "error" : "warning"}
+                variant="outlined"
+                onClick={() => setDiagnosticOpen(true)}
+              />
+            )}
+            <Chip
+              label={t("Enhance Trace")}
+              size="small"
+              variant="outlined"
+              onClick={() => setTraceOpen(true)}
+            />
+            <Box display="flex" gap={1} ml="auto">
+              {EXPORT_FORMATS.map(([format, label]) => (
+                <Chip
+                  key={format}
+                  label={t("Copy as _format", { format: t(label) })}
+                  size="small"
+                  variant="outlined"
+                  onClick={() => onExport(format)}
+                />
+              ))}
+            </Box>
+          </Box>
+        }
+        initialData={Promise.resolve(runtimeConfig)}
+        readOnly
+        language="yaml"
+        schema="clash"
+        onClose={() => setOpen(false)}
+      />
+      <LogViewer
+        open={diagnosticOpen}
+        title={t("Config Problems")}
+        logInfo={diagnostics.map((d) => [
+          d.level,
+          `${d.path}: ${d.message}`,
+        ])}
+        onClose={() => setDiagnosticOpen(false)}
+      />
+      <TraceViewer open={traceOpen} onClose={() => setTraceOpen(false)} />
+    </>
   );
 });
@@ -28,6 +28,8 @@ export const MiscViewer = forwardRef<DialogRef>((props, ref) => {
     defaultLatencyTest: "",
     autoLogClean: 0,
     defaultLatencyTimeout: 10000,
+    profileMaxSize: 20,
+    profileHistoryLimit: 10,
   });
 
   useImperativeHandle(ref, () => ({
@@ -42,6 +44,8 @@ export const MiscViewer = forwardRef<DialogRef>((props, ref) => {
         defaultLatencyTest: verge?.default_latency_test || "",
         autoLogClean: verge?.auto_log_clean || 0,
         defaultLatencyTimeout: verge?.default_latency_timeout || 10000,
+        profileMaxSize: verge?.profile_max_size || 20,
+        profileHistoryLimit: verge?.profile_history_limit ?? 10,
       });
     },
     close: () => setOpen(false),
@@ -57,6 +61,10 @@ export const MiscViewer = forwardRef<DialogRef>((props, ref) => {
         proxy_layout_column: values.proxyLayoutColumn,
         default_latency_test: values.defaultLatencyTest,
         default_latency_timeout: values.defaultLatencyTimeout,
+        profile_max_size: Number.isFinite(values.profileMaxSize)
+          ? Math.max(1, values.profileMaxSize)
+          : 20,
+        profile_history_limit: values.profileHistoryLimit,
         auto_log_clean: values.autoLogClean as any,
       });
       setOpen(false);
@@ -246,6 +254,51 @@ export const MiscViewer = forwardRef<DialogRef>((props, ref) => {
             }}
           />
         </ListItem>
+
+        <ListItem sx={{ padding: "5px 2px" }}>
+          <ListItemText primary={t("Profile Max Size")} />
+          <TextField
+            autoComplete="new-password"
+            size="small"
+            type="number"
+            autoCorrect="off"
+            autoCapitalize="off"
+            spellCheck="false"
+            sx={{ width: 250 }}
+            value={values.profileMaxSize}
+            placeholder="20"
+            onChange={(e) =>
+              setValues((v) => ({
+                ...v,
+                profileMaxSize: parseInt(e.target.value),
+              }))
+            }
+            InputProps={{
+              endAdornment: <InputAdornment position="end">MB</InputAdornment>,
+            }}
+          />
+        </ListItem>
+
+        <ListItem sx={{ padding: "5px 2px" }}>
+          <ListItemText primary={t("Profile History Limit")} />
+          <TextField
+            autoComplete="new-password"
+            size="small"
+            type="number"
+            autoCorrect="off"
+            autoCapitalize="off"
+            spellCheck="false"
+            sx={{ width: 250 }}
+            value={values.profileHistoryLimit}
+            placeholder="10"
+            onChange={(e) =>
+              setValues((v) => ({
+                ...v,
+                profileHistoryLimit: parseInt(e.target.value),
+              }))
+            }
+          />
+        </ListItem>
       </List>
     </BaseDialog>
   );
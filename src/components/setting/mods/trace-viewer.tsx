@@ -0,0 +1,102 @@
+import { Fragment, useEffect, useState } from "react";
+import { useTranslation } from "react-i18next";
+import {
+  Box,
+  Button,
+  Dialog,
+  DialogActions,
+  DialogContent,
+  DialogTitle,
+  Divider,
+  Typography,
+} from "@mui/material";
+import { BaseEmpty, Notice } from "@/components/base";
+import { getEnhanceTrace } from "@/services/cmds";
+
+interface Props {
+  open: boolean;
+  onClose: () => void;
+}
+
+// what every step of the enhancement changed
+export const TraceViewer = (props: Props) => {
+  const { open, onClose } = props;
+
+  const { t } = useTranslation();
+  const [steps, setSteps] = useState<ITraceStep[]>([]);
+
+  useEffect(() => {
+    if (!open) return;
+    getEnhanceTrace()
+      .then(setSteps)
+      .catch((err) => Notice.error(err?.message || err.toString()));
+  }, [open]);
+
+  const lines = (step: ITraceStep) =>
+    (
+      [
+        ["Keys", step.keys],
+        ["Proxies", step.proxies],
+        ["Proxy Groups", step.groups],
+        ["Rules", step.rules],
+      ] as [string, IProfileChanges][]
+    ).flatMap(([label, changes]) =>
+      [
+        ...changes.added.map((name) => `+ ${name}`),
+        ...changes.removed.map((name) => `- ${name}`),
+        ...changes.changed.map((name) => `~ ${name}`),
+      ].map((line) => `${t(label)}: ${line}`),
+    );
+
+  return (
+    <Dialog open={open} onClose={onClose}>
+      <DialogTitle>{t("Enhance Trace")}</DialogTitle>
+
+      <DialogContent
+        sx={{ width: 480, height: 360, userSelect: "text", pb: 1 }}
+      >
+        {steps.map((step, index) => {
+          const changes = lines(step);
+          return (
+            <Fragment key={index}>
+              <Box display="flex" alignItems="center" gap={1}>
+                <Typography flex={1}>
+                  {step.step}
+                  {step.uid && (
+                    <Typography
+                      component="span"
+                      color="text.secondary"
+                      fontSize={12}
+                      ml={1}
+                    >
+                      {step.uid}
+                    </Typography>
+                  )}
+                </Typography>
+                <Typography color="text.secondary" fontSize={12}>
+                  {(step.elapsed / 1000).toFixed(1)} ms
+                </Typography>
+              </Box>
+              <Typography
+                color="text.secondary"
+                fontSize={12}
+                whiteSpace="pre-wrap"
+              >
+                {changes.length > 0 ? changes.join("\n") : t("No Changes")}
+              </Typography>
+              <Divider sx={{ my: 0.5 }} />
+            </Fragment>
+          );
+        })}
+
+        {steps.length === 0 && <BaseEmpty />}
+      </DialogContent>
+
+      <DialogActions>
+        <Button onClick={onClose} variant="outlined">
+          {t("Close")}
+        </Button>
+      </DialogActions>
+    </Dialog>
+  );
+};
@@ -57,6 +57,9 @@ const handleNoticeMessage = (
     case "set_config::error":
       Notice.error(msg);
       break;
+    case "update_profile::error":
+      Notice.error(`${t("Profile Update Failed")} ${msg}`);
+      break;
     case "config_validate::boot_error":
       Notice.error(`${t("Boot Config Validation Failed")} ${msg}`);
       break;
@@ -1,4 +1,4 @@
-import useSWR from "swr";
+import useSWR, { mutate } from "swr";
 import { useEffect, useMemo, useRef, useState } from "react";
 import { useLockFn } from "ahooks";
 import { Box, Button, IconButton, Stack, Divider, Grid2 } from "@mui/material";
@@ -112,7 +112,6 @@ const ProfilePage = () => {
 
   const {
     profiles = {},
-    activateSelected,
     patchProfiles,
     mutateProfiles,
   } = useProfiles();
@@ -122,6 +121,28 @@ const ProfilePage = () => {
     getRuntimeLogs,
   );
 
+  // 导入时保存的转换警告，加上增强时的日志（只有当前订阅才有）
+  const profileLogs = (item: IProfileItem): [string, string][] => {
+    const warnings = (item.warnings ?? []).map(
+      (warning): [string, string] => ["warn", warning],
+    );
+    const { rules, proxies, groups, transform } = item.option ?? {};
+    const logs = [item.uid, rules, proxies, transform, groups]
+      .flatMap((uid) => chainLogs[uid ?? ""] ?? [])
+      .filter(
+        ([level, log]) => level !== "warn" || !item.warnings?.includes(log),
+      );
+    return [...warnings, ...logs];
+  };
+
+  // 全局脚本的日志，加上内建脚本（uid 以 verge_ 开头）的错误
+  const scriptLogs = [
+    ...(chainLogs["Script"] ?? []),
+    ...Object.entries(chainLogs)
+      .filter(([uid]) => uid.startsWith("verge_"))
+      .flatMap(([, logs]) => logs),
+  ];
+
   const viewerRef = useRef<ProfileViewerRef>(null);
   const configRef = useRef<DialogRef>(null);
 
@@ -129,7 +150,7 @@ const ProfilePage = () => {
   const profileItems = useMemo(() => {
     const items = profiles.items || [];
 
-    const type1 = ["local", "remote"];
+    const type1 = ["local", "remote", "aggregate"];
 
     return items.filter((i) => i && type1.includes(i.type!));
   }, [profiles]);
@@ -178,7 +199,8 @@ const ProfilePage = () => {
       const success = await patchProfiles({ current: profile });
       await mutateLogs();
       closeAllConnections();
-      await activateSelected();
+      // 分组节点的恢复由后端在配置重载后完成
+      mutate("getProxies");
       if (notifySuccess && success) {
         Notice.success(t("Profile Switched"), 1000);
       }
@@ -550,6 +572,7 @@ rules:
                       selected={profiles.current === item.uid}
                       activating={activatings.includes(item.uid)}
                       itemData={item}
+                      logInfo={profileLogs(item)}
                       onSelect={(f) => onSelect(item.uid, f)}
                       onEdit={() => viewerRef.current?.edit(item)}
                       onSave={async (prev, curr) => {
@@ -586,7 +609,7 @@ rules:
               <Grid2 size={{ xs: 12, sm: 6, md: 6, lg: 6 }}>
                 <ProfileMore
                   id="Script"
-                  logInfo={chainLogs["Script"]}
+                  logInfo={scriptLogs}
                   onSave={async (prev, curr) => {
                     if (prev !== curr) {
                       await onEnhance(false);
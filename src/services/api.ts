@@ -1,5 +1,5 @@
 import axios, { AxiosInstance } from "axios";
-import { getClashInfo } from "./cmds";
+import { cmdGetGroupDelay, getClashInfo } from "./cmds";
 import { invoke } from "@tauri-apps/api/core";
 import { useLockFn } from "ahooks";
 
@@ -204,9 +204,9 @@ export const getProxyProviders = async () => {
 };
 
 export const getRuleProviders = async () => {
-  const instance = await getAxios();
-  const response = await instance.get<any, any>("/providers/rules");
-
+  const response = await invoke<{
+    providers: Record<string, IRuleProviderItem>;
+  }>("get_rule_providers");
   const providers = (response.providers || {}) as Record<
     string,
     IRuleProviderItem
@@ -222,20 +222,15 @@ export const getRuleProviders = async () => {
 
 // proxy providers health check
 export const providerHealthCheck = async (name: string) => {
-  const instance = await getAxios();
-  return instance.get(
-    `/providers/proxies/${encodeURIComponent(name)}/healthcheck`,
-  );
+  return invoke<void>("healthcheck_proxy_provider", { name });
 };
 
 export const proxyProviderUpdate = async (name: string) => {
-  const instance = await getAxios();
-  return instance.put(`/providers/proxies/${encodeURIComponent(name)}`);
+  return invoke<void>("update_proxy_provider", { name });
 };
 
 export const ruleProviderUpdate = async (name: string) => {
-  const instance = await getAxios();
-  return instance.put(`/providers/rules/${encodeURIComponent(name)}`);
+  return invoke<void>("update_rule_provider", { name });
 };
 
 export const getConnections = async () => {
@@ -262,31 +257,21 @@ export const getGroupProxyDelays = async (
   url?: string,
   timeout?: number,
 ) => {
-  const params = {
-    timeout: timeout || 10000,
-    url: url || "http://cp.cloudflare.com/generate_204",
-  };
+  const testUrl = url || "http://cp.cloudflare.com/generate_204";
+  const testTimeout = timeout || 10000;
 
   console.log(
-    `[API] 获取代理组延迟，组: ${groupName}, URL: ${params.url}, 超时: ${params.timeout}ms`,
+    `[API] 获取代理组延迟，组: ${groupName}, URL: ${testUrl}, 超时: ${testTimeout}ms`,
   );
 
   try {
-    const instance = await getAxios();
-    console.log(
-      `[API] 发送HTTP请求: GET /group/${encodeURIComponent(groupName)}/delay`,
-    );
-
-    const result = await instance.get(
-      `/group/${encodeURIComponent(groupName)}/delay`,
-      { params },
-    );
+    const result = await cmdGetGroupDelay(groupName, testTimeout, testUrl);
 
     console.log(
       `[API] 获取代理组延迟成功，组: ${groupName}, 结果数量:`,
       Object.keys(result || {}).length,
     );
-    return result as any as Record<string, number>;
+    return result;
   } catch (error) {
     console.error(`[API] 获取代理组延迟失败，组: ${groupName}`, error);
     throw error;
@@ -79,6 +79,22 @@ export async function getRuntimeYaml() {
   return invoke<string | null>("get_runtime_yaml");
 }
 
+export async function getProfileVersions(uid: string) {
+  return invoke<IProfileVersion[]>("get_profile_versions", { uid });
+}
+
+export async function diffProfileVersion(uid: string, version: string) {
+  return invoke<IProfileDiff>("diff_profile_version", { uid, version });
+}
+
+export async function restoreProfileVersion(uid: string, version: string) {
+  return invoke<void>("restore_profile_version", { uid, version });
+}
+
+export async function exportRuntimeConfig(format: IExportFormat) {
+  return invoke<IExported>("export_runtime_config", { format });
+}
+
 export async function getRuntimeExists() {
   return invoke<string[]>("get_runtime_exists");
 }
@@ -87,6 +103,14 @@ export async function getRuntimeLogs() {
   return invoke<Record<string, [string, string][]>>("get_runtime_logs");
 }
 
+export async function getRuntimeDiagnostics() {
+  return invoke<IDiagnostic[]>("get_runtime_diagnostics");
+}
+
+export async function getEnhanceTrace() {
+  return invoke<ITraceStep[]>("get_enhance_trace");
+}
+
 export async function patchClashConfig(payload: Partial<IConfigData>) {
   return invoke<void>("patch_clash_config", { payload });
 }
@@ -166,6 +190,11 @@ export async function openWebUrl(url: string) {
   return invoke<void>("open_web_url", { url });
 }
 
+/// select a node for the group, connections are closed by the backend
+export async function selectProxy(group: string, proxy: string) {
+  return invoke<void>("select_proxy", { group, proxy });
+}
+
 export async function cmdGetProxyDelay(
   name: string,
   timeout: number,
@@ -178,7 +207,6 @@ export async function cmdGetProxyDelay(
   );
 
   try {
-    name = encodeURIComponent(name);
     const result = await invoke<{ delay: number }>(
       "clash_api_get_proxy_delay",
       {
@@ -209,6 +237,50 @@ export async function cmdGetProxyDelay(
   }
 }
 
+/// test many nodes in the backend, results arrive as `verge://proxy-delay` events
+export async function cmdTestProxiesDelay(
+  group: string,
+  names: string[],
+  timeout: number,
+  url?: string,
+  concurrency?: number,
+) {
+  return invoke<void>("test_proxies_delay", {
+    group,
+    names,
+    url,
+    timeout,
+    concurrency,
+  });
+}
+
+export async function cmdGetGroupDelay(
+  group: string,
+  timeout: number,
+  url?: string,
+  expected?: string,
+) {
+  return invoke<Record<string, number>>("clash_api_get_group_delay", {
+    group,
+    url,
+    timeout,
+    expected,
+  });
+}
+
+export async function dnsQuery(name: string, recordType?: string) {
+  return invoke<IDnsQuery>("dns_query", { name, recordType });
+}
+
+export async function flushFakeIp() {
+  return invoke<void>("flush_fakeip");
+}
+
+/// resolve through the core and the system resolver and compare
+export async function diagnoseDns(domain: string) {
+  return invoke<IDnsDiagnosis>("diagnose_dns", { domain });
+}
+
 export async function cmdTestDelay(url: string) {
   return invoke<number>("test_delay", { url });
 }
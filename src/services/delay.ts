@@ -1,4 +1,5 @@
-import { cmdGetProxyDelay } from "./cmds";
+import { listen } from "@tauri-apps/api/event";
+import { cmdGetProxyDelay, cmdTestProxiesDelay } from "./cmds";
 
 const hashKey = (name: string, group: string) => `${group ?? ""}::${name}`;
 
@@ -147,7 +148,7 @@ class DelayManager {
     nameList: string[],
     group: string,
     timeout: number,
-    concurrency = 36,
+    concurrency = 10,
   ) {
     console.log(
       `[DelayManager] 批量测试延迟开始，组: ${group}, 数量: ${nameList.length}, 并发数: ${concurrency}`,
@@ -156,50 +157,37 @@ class DelayManager {
     // 设置正在延迟测试中
     names.forEach((name) => this.setDelay(name, group, -2));
 
-    let index = 0;
     const startTime = Date.now();
     const listener = this.groupListenerMap.get(group);
 
-    const help = async (): Promise<void> => {
-      const currName = names[index++];
-      if (!currName) return;
-
-      try {
-        // 确保API调用前状态为测试中
-        this.setDelay(currName, group, -2);
-
-        // 添加一些随机延迟，避免所有请求同时发出和返回
-        if (index > 1) {
-          // 第一个不延迟，保持响应性
-          await new Promise((resolve) =>
-            setTimeout(resolve, Math.random() * 200),
-          );
-        }
-
-        await this.checkDelay(currName, group, timeout);
+    // 后端限制并发，每个节点测完就通过事件推送结果
+    const unlisten = await listen<[string, string, number | null]>(
+      "verge://proxy-delay",
+      (event) => {
+        const [eventGroup, name, delay] = event.payload;
+        if (eventGroup !== group) return;
+        this.setDelay(name, group, delay ?? 1e6);
         if (listener) listener();
-      } catch (error) {
-        console.error(
-          `[DelayManager] 批量测试单个代理出错，代理: ${currName}`,
-          error,
-        );
-        // 设置为错误状态
-        this.setDelay(currName, group, 1e6);
-      }
-
-      return help();
-    };
-
-    // 限制并发数，避免发送太多请求
-    const actualConcurrency = Math.min(concurrency, names.length, 10);
-    console.log(`[DelayManager] 实际并发数: ${actualConcurrency}`);
+      },
+    );
 
-    const promiseList: Promise<void>[] = [];
-    for (let i = 0; i < actualConcurrency; i++) {
-      promiseList.push(help());
+    try {
+      await cmdTestProxiesDelay(
+        group,
+        names,
+        timeout,
+        this.getUrl(group),
+        concurrency,
+      );
+    } catch (error) {
+      console.error(`[DelayManager] 批量测试延迟出错，组: ${group}`, error);
+      names
+        .filter((name) => this.getDelay(name, group) === -2)
+        .forEach((name) => this.setDelay(name, group, 1e6));
+    } finally {
+      unlisten();
     }
 
-    await Promise.all(promiseList);
     const totalTime = Date.now() - startTime;
     console.log(
       `[DelayManager] 批量测试延迟完成，组: ${group}, 总耗时: ${totalTime}ms`,
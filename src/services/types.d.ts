@@ -165,6 +165,26 @@ interface IConnections {
   connections: IConnectionsItem[];
 }
 
+interface IDnsRecord {
+  name: string;
+  type: number; // 1 A, 28 AAAA, 5 CNAME
+  TTL: number;
+  data: string;
+}
+
+interface IDnsQuery {
+  Status: number;
+  TC: boolean;
+  RD: boolean;
+  RA: boolean;
+  AD: boolean;
+  CD: boolean;
+  Question: { Name: string; Qtype: number; Qclass: number }[];
+  Answer: IDnsRecord[];
+  Authority: IDnsRecord[];
+  Additional: IDnsRecord[];
+}
+
 /**
  * Some interface for command
  */
@@ -180,9 +200,21 @@ interface IClashInfo {
   secret?: string;
 }
 
+interface IDnsDiagnosis {
+  domain: string;
+  core: string[];
+  core_error?: string | null;
+  system: string[];
+  system_error?: string | null;
+  fake_ip: boolean; // core answered from the fake-ip pool
+  consistent: boolean;
+  only_core: string[];
+  only_system: string[];
+}
+
 interface IProfileItem {
   uid: string;
-  type?: "local" | "remote" | "merge" | "script";
+  type?: "local" | "remote" | "aggregate" | "merge" | "script";
   name?: string;
   desc?: string;
   file?: string;
@@ -200,6 +232,53 @@ interface IProfileItem {
   };
   option?: IProfileOption;
   home?: string;
+  warnings?: string[];
+  fetch?: {
+    path?: "direct" | "self-proxy" | "system-proxy";
+    errors: string[];
+  };
+}
+
+interface IProfileVersion {
+  id: string;
+  updated: number;
+  size: number;
+}
+
+interface IProfileChanges {
+  added: string[];
+  removed: string[];
+  changed: string[];
+}
+
+interface IProfileDiff {
+  proxies: IProfileChanges;
+  groups: IProfileChanges;
+  rules: [number, number];
+}
+
+interface IDiagnostic {
+  level: "error" | "warn";
+  path: string;
+  message: string;
+}
+
+interface ITraceStep {
+  step: string;
+  uid: string | null;
+  // microseconds
+  elapsed: number;
+  keys: IProfileChanges;
+  proxies: IProfileChanges;
+  groups: IProfileChanges;
+  rules: IProfileChanges;
+}
+
+type IExportFormat = "sing-box" | "share-links" | "base64";
+
+interface IExported {
+  data: string;
+  warnings: string[];
 }
 
 interface IProfileOption {
@@ -213,6 +292,8 @@ interface IProfileOption {
   rules?: string;
   proxies?: string;
   groups?: string;
+  transform?: string;
+  sources?: string[];
 }
 
 interface IProfilesConfig {
@@ -246,10 +327,16 @@ interface INetworkInterface {
   index: number;
 }
 
+interface ISeqReplace {
+  match: string;
+  with: any;
+}
+
 interface ISeqProfileConfig {
   prepend: [];
   append: [];
   delete: [];
+  replace?: ISeqReplace[];
 }
 
 interface IProxyGroupConfig {
@@ -780,6 +867,8 @@ interface IVergeConfig {
   auto_check_update?: boolean;
   default_latency_test?: string;
   default_latency_timeout?: number;
+  profile_max_size?: number;
+  profile_history_limit?: number;
   enable_builtin_enhanced?: boolean;
   auto_log_clean?: 0 | 1 | 2 | 3;
   proxy_layout_column?: number;